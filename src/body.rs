@@ -1,27 +1,110 @@
-use cgmath::Rad;
-use super::scene::GRAVITY;
+use cgmath::{Rad, Matrix, InnerSpace, dot};
+use super::scene::{GRAVITY, EPSILON};
 use super::types::{Vec2, Mat2, Real, PI};
-use super::operations::cross_vectors;
+use std::f32::consts::SQRT_2;
+use super::operations::{cross_vectors, cross_real_vector, dist_sqr, clip_polygon, polygon_area_centroid, closest_point_segment, convex_decomposition};
+
+// Segments used when a circle has to be treated as a polygon
+const CIRCLE_SEGMENTS: usize = 32;
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct PolygonShapeVertex {
     pub position: Vec2,
     pub normal: Vec2,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub enum Shape {
     Circle {
         radius: Real
     },
+    // Convex polygon in counter clockwise order. A radius above zero rounds it off,
+    // inflating it by radius like a capsule.
     Polygon {
         orientation: Mat2,
         vertices: Vec<PolygonShapeVertex>,
+        radius: Real,
+    },
+    // Segment from (0, -half_height) to (0, half_height) inflated by radius
+    Capsule {
+        half_height: Real,
+        radius: Real,
+    },
+    // Two sided line segment, b2EdgeShape
+    Edge {
+        vertices: (Vec2, Vec2),
     },
+    // Connected one sided segments, b2ChainShape. Bodies collide on the side the segment
+    // normal (e.y, -e.x) points to, which is -y for a chain going towards +x, up on the
+    // screen since y points down. Bodies on the other side pass through.
+    Chain {
+        vertices: Vec<Vec2>,
+        looped: bool,
+    },
+    // Several shapes on one body, each colliding on its own
+    Compound {
+        fixtures: Vec<Fixture>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Material {
+    pub static_friction: Real,
+    pub dynamic_friction: Real,
+    pub restitution: Real,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            static_friction: 0.5,
+            dynamic_friction: 0.3,
+            restitution: 0.2,
+        }
+    }
+}
+
+// A shape of a compound body placed relative to the compound shape's origin, b2Fixture
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Fixture {
+    pub shape: Shape,
+    pub offset: Vec2,
+    pub rotation: Rad<Real>,
+    pub density: Real,
+    pub material: Material,
+}
+
+impl Fixture {
+    pub fn new<T: Into<Rad<Real>>>(shape: Shape, offset: Vec2, rotation: T) -> Self {
+        Fixture {
+            shape: shape,
+            offset: offset,
+            rotation: rotation.into(),
+            density: 1.0,
+            material: Material::default(),
+        }
+    }
+}
+
+// A segment of an edge or chain in body space. Chain segments know their neighbours
+// through the ghost vertices, so bodies don't catch on the inner corners.
+#[derive(Clone, Debug)]
+pub struct EdgeSegment {
+    pub vertex0: Option<Vec2>,
+    pub vertex1: Vec2,
+    pub vertex2: Vec2,
+    pub vertex3: Option<Vec2>,
+    pub one_sided: bool,
 }
 
 impl Shape {
     pub fn rect(h: Vec2) -> Shape {
+        assert!(h.x > 0.0 && h.y > 0.0);
+
         Shape::Polygon {
             orientation: Mat2::new(1.0, 0.0, 0.0, 1.0),
             vertices: vec![
@@ -30,21 +113,260 @@ impl Shape {
                 PolygonShapeVertex { position: Vec2::new( h.x,  h.y), normal: Vec2::new( 0.0,  1.0) },
                 PolygonShapeVertex { position: Vec2::new(-h.x,  h.y), normal: Vec2::new(-1.0,  0.0) },
             ],
+            radius: 0.0,
+        }
+    }
+
+    // A rect inflated by radius, b2MakeRoundedBox
+    pub fn rounded_rect(h: Vec2, radius: Real) -> Shape {
+        Shape::rounded_polygon(&Shape::rect(h).outline(), radius)
+    }
+
+    // A rect centered on center and turned by angle in body space, b2MakeOffsetBox
+    pub fn offset_rect<T: Into<Rad<Real>>>(h: Vec2, center: Vec2, angle: T) -> Shape {
+        let rotation = Mat2::from_angle(angle.into());
+        let points = Shape::rect(h).outline().into_iter()
+            .map(|p| rotation * p + center)
+            .collect::<Vec<_>>();
+        Shape::polygon(&points)
+    }
+
+    // A triangle from its corners in either order
+    pub fn triangle(a: Vec2, b: Vec2, c: Vec2) -> Shape {
+        if cross_vectors(b - a, c - a) < 0.0 {
+            Shape::polygon(&[a, c, b])
+        } else {
+            Shape::polygon(&[a, b, c])
+        }
+    }
+
+    // A polygon with equal sides and its corners radius away from the center
+    pub fn regular_polygon(sides: usize, radius: Real) -> Shape {
+        assert!(sides >= 3);
+        assert!(radius > 0.0);
+
+        let points = (0..sides).map(|i| {
+            let angle = 2.0 * PI * i as Real / sides as Real;
+            Vec2::new(angle.cos(), angle.sin()) * radius
+        }).collect::<Vec<_>>();
+        Shape::polygon(&points)
+    }
+
+    // An ellipse with the half extents h, approximated by a polygon with the corners on the ellipse
+    pub fn ellipse(h: Vec2, segments: usize) -> Shape {
+        assert!(segments >= 3);
+        assert!(h.x > 0.0 && h.y > 0.0);
+
+        let points = (0..segments).map(|i| {
+            let angle = 2.0 * PI * i as Real / segments as Real;
+            Vec2::new(h.x * angle.cos(), h.y * angle.sin())
+        }).collect::<Vec<_>>();
+        Shape::polygon(&points)
+    }
+
+    // A convex polygon from its points in counter clockwise order
+    pub fn polygon(points: &[Vec2]) -> Shape {
+        Shape::rounded_polygon(points, 0.0)
+    }
+
+    // A convex polygon inflated by radius, b2MakePolygon. Panics on points that aren't
+    // a convex polygon in counter clockwise order without repeated or collinear points.
+    pub fn rounded_polygon(points: &[Vec2], radius: Real) -> Shape {
+        assert!(points.len() >= 3);
+        assert!(radius >= 0.0);
+        for i in 0..points.len() {
+            let (a, b, c) = (points[i], points[(i + 1) % points.len()], points[(i + 2) % points.len()]);
+            assert!(dist_sqr(a, b) > EPSILON);
+            assert!(cross_vectors(b - a, c - b) > 0.0);
+            assert!(points.iter().all(|&p| cross_vectors(b - a, p - a) >= 0.0));
+        }
+
+        Shape::Polygon {
+            orientation: Mat2::new(1.0, 0.0, 0.0, 1.0),
+            vertices: (0..points.len()).map(|i| {
+                let edge = points[(i + 1) % points.len()] - points[i];
+                PolygonShapeVertex {
+                    position: points[i],
+                    normal: Vec2::new(edge.y, -edge.x).normalize(),
+                }
+            }).collect(),
+            radius: radius,
+        }
+    }
+
+    // Any simple polygon, split into convex polygons. Polygons that are already
+    // convex stay a single polygon, others become a compound shape. Panics when the
+    // outline crosses itself.
+    pub fn concave(points: &[Vec2]) -> Shape {
+        let pieces = convex_decomposition(points);
+        if pieces.len() == 1 {
+            return Shape::polygon(&pieces[0])
+        }
+
+        Shape::Compound {
+            fixtures: pieces.into_iter().map(|piece| {
+                Fixture::new(Shape::polygon(&piece), Vec2::new(0.0, 0.0), Rad(0.0))
+            }).collect(),
+        }
+    }
+
+    // Scales the shape around its origin
+    pub fn scale(&mut self, factor: Real) {
+        assert!(factor > 0.0);
+
+        match self {
+            &mut Shape::Circle { ref mut radius } => *radius *= factor,
+            &mut Shape::Polygon { ref mut vertices, ref mut radius, .. } => {
+                for vertex in vertices {
+                    vertex.position *= factor;
+                }
+                *radius *= factor;
+            }
+            &mut Shape::Capsule { ref mut half_height, ref mut radius } => {
+                *half_height *= factor;
+                *radius *= factor;
+            }
+            &mut Shape::Edge { ref mut vertices } => {
+                vertices.0 *= factor;
+                vertices.1 *= factor;
+            }
+            &mut Shape::Chain { ref mut vertices, .. } => {
+                for vertex in vertices {
+                    *vertex *= factor;
+                }
+            }
+            &mut Shape::Compound { ref mut fixtures } => {
+                for fixture in fixtures {
+                    fixture.offset *= factor;
+                    fixture.shape.scale(factor);
+                }
+            }
+        }
+    }
+
+    // Points around the shape in body space, counter clockwise. Curves are split
+    // into segments.
+    pub fn outline(&self) -> Vec<Vec2> {
+        fn arc(center: Vec2, radius: Real, start: Real, sweep: Real, segments: usize) -> Vec<Vec2> {
+            (0..segments + 1).map(|i| {
+                let angle = start + sweep * i as Real / segments as Real;
+                center + Vec2::new(angle.cos(), angle.sin()) * radius
+            }).collect()
+        }
+
+        match self {
+            &Shape::Circle { radius } => {
+                let mut points = arc(Vec2::new(0.0, 0.0), radius, 0.0, PI, CIRCLE_SEGMENTS / 2);
+                points.pop();
+                points.extend(arc(Vec2::new(0.0, 0.0), radius, PI, PI, CIRCLE_SEGMENTS / 2));
+                points.pop();
+                points
+            }
+            &Shape::Polygon { ref vertices, radius, .. } => {
+                if radius <= 0.0 {
+                    return vertices.iter().map(|v| v.position).collect()
+                }
+
+                // Round each corner from the normal of the edge before it to the normal of the edge after it
+                let mut points = Vec::new();
+                for (i, v) in vertices.iter().enumerate() {
+                    let previous = vertices[(i + vertices.len() - 1) % vertices.len()].normal;
+                    let start = previous.y.atan2(previous.x);
+                    let mut sweep = v.normal.y.atan2(v.normal.x) - start;
+                    if sweep < 0.0 {
+                        sweep += 2.0 * PI;
+                    }
+                    let segments = ((sweep / (2.0 * PI) * CIRCLE_SEGMENTS as Real).ceil() as usize).max(1);
+                    points.extend(arc(v.position, radius, start, sweep, segments));
+                }
+                points
+            }
+            &Shape::Capsule { half_height, radius } => {
+                let mut points = arc(Vec2::new(0.0, half_height), radius, 0.0, PI, CIRCLE_SEGMENTS / 2);
+                points.extend(arc(Vec2::new(0.0, -half_height), radius, PI, PI, CIRCLE_SEGMENTS / 2));
+                points
+            }
+            &Shape::Edge { vertices: (v1, v2) } => vec![v1, v2],
+            &Shape::Chain { ref vertices, .. } => vertices.clone(),
+            // Not a polygon, just every point of the fixtures
+            &Shape::Compound { ref fixtures } => {
+                fixtures.iter().flat_map(|fixture| {
+                    let rotation = Mat2::from_angle(fixture.rotation);
+                    fixture.shape.outline().into_iter().map(move |p| rotation * p + fixture.offset)
+                }).collect()
+            }
+        }
+    }
+
+    // The segments of an edge or chain, b2ChainShape::GetChildEdge
+    pub fn edges(&self) -> Vec<EdgeSegment> {
+        match self {
+            &Shape::Edge { vertices: (v1, v2) } => vec![EdgeSegment {
+                vertex0: None,
+                vertex1: v1,
+                vertex2: v2,
+                vertex3: None,
+                one_sided: false,
+            }],
+            &Shape::Chain { ref vertices, looped } => {
+                let n = vertices.len();
+                let count = if looped { n } else { n.saturating_sub(1) };
+
+                (0..count).map(|i| {
+                    let previous = if i > 0 { Some(vertices[i - 1]) } else if looped { Some(vertices[n - 1]) } else { None };
+                    let next = if i + 2 < n { Some(vertices[i + 2]) } else if looped { Some(vertices[(i + 2) % n]) } else { None };
+
+                    EdgeSegment {
+                        vertex0: previous,
+                        vertex1: vertices[i],
+                        vertex2: vertices[(i + 1) % n],
+                        vertex3: next,
+                        one_sided: true,
+                    }
+                }).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn area(&self) -> Real {
+        match self {
+            &Shape::Circle { radius } => PI * radius * radius,
+            &Shape::Polygon { .. } => polygon_area_centroid(&self.outline()).0,
+            &Shape::Capsule { half_height, radius } => PI * radius * radius + 4.0 * half_height * radius,
+            &Shape::Edge { .. } | &Shape::Chain { .. } => 0.0,
+            &Shape::Compound { ref fixtures } => fixtures.iter().map(|fixture| fixture.shape.area()).sum(),
         }
     }
 }
 
-struct MassData {
+// The inertia is about the center of mass, which is in the shape's space
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct MassData {
     pub moment_inertia: Real,
     pub inv_inertia: Real,
     pub mass: Real,
     pub inv_mass: Real,
+    pub center: Vec2,
+}
+
+impl MassData {
+    pub fn new(mass: Real, moment_inertia: Real, center: Vec2) -> Self {
+        MassData {
+            moment_inertia: moment_inertia,
+            inv_inertia: if moment_inertia != 0.0 { 1.0 / moment_inertia } else { 0.0 },
+            mass: mass,
+            inv_mass: if mass != 0.0 { 1.0 / mass } else { 0.0 },
+            center: center,
+        }
+    }
 }
 
 // Circle::ComputeMass
-fn compute_mass(shape: &mut Shape, density: Real) -> MassData {
+pub fn compute_mass(shape: &Shape, density: Real) -> MassData {
     match shape {
-        &mut Shape::Circle{ radius } => {
+        &Shape::Circle{ radius } => {
             let m = PI * radius * radius * density;
             let i = m * radius * radius;
 
@@ -53,55 +375,211 @@ fn compute_mass(shape: &mut Shape, density: Real) -> MassData {
                 inv_inertia: if i != 0.0 { 1.0 / i } else { 0.0 },
                 mass: m,
                 inv_mass: if m != 0.0 { 1.0 / m } else { 0.0 },
+                center: Vec2::new(0.0, 0.0),
             }
         }
-        &mut Shape::Polygon { ref mut vertices, .. } => {
+        // b2PolygonShape::ComputeMass
+        &Shape::Polygon { ref vertices, radius, .. } => {
             let mut c = Vec2::new(0.0, 0.0); // centroid
             let mut area = 0.0;
             let mut i = 0.0;
             let k_inv3 = 1.0 / 3.0;
 
-            for edge in vertices.chunks(2) {
-                let p1 = edge[0].position;
-                let p2 = if edge.len() == 2 { edge[1].position } else { edge[0].position };
-                
-                let d = cross_vectors(p1, p2);
+            // Rounded polygons are approximated by pushing the vertices out along their corners
+            let points = (0..vertices.len()).map(|index| {
+                let previous = vertices[(index + vertices.len() - 1) % vertices.len()].normal;
+                let v = &vertices[index];
+                if radius > 0.0 {
+                    v.position + (previous + v.normal).normalize() * (SQRT_2 * radius)
+                } else {
+                    v.position
+                }
+            }).collect::<Vec<_>>();
+
+            // Reference point for the triangles, keeps the sums precise far from the origin
+            let s = points[0];
+
+            for index in 0..points.len() {
+                let e1 = points[index] - s;
+                let e2 = points[(index + 1) % points.len()] - s;
+
+                let d = cross_vectors(e1, e2);
                 let triangle_area = 0.5 * d;
 
                 area += triangle_area;
                 // Use area to weight the centroid average, not just vertex position
-                c += triangle_area * k_inv3 * (p1 + p2);
+                c += triangle_area * k_inv3 * (e1 + e2);
 
-                let intx2 = p1.x * p1.x + p2.x * p1.x + p2.x * p2.x;
-                let inty2 = p1.y * p1.y + p2.y * p1.y + p2.y * p2.y;
+                let intx2 = e1.x * e1.x + e2.x * e1.x + e2.x * e2.x;
+                let inty2 = e1.y * e1.y + e2.y * e1.y + e2.y * e2.y;
                 i += (0.25 * k_inv3 * d) * (intx2 + inty2);
             }
 
             c *= 1.0 / area;
 
-            // Translate vertices to centroid (make the centroid (0, 0)
-            // for the polygon in model space)
-            for vertex in vertices {
-               vertex.position -= c;
+            let m = density * area;
+            // Inertia about the centroid instead of the reference point
+            let i = density * i - m * dot(c, c);
+
+            MassData {
+                moment_inertia: i,
+                inv_inertia: if i != 0.0 { 1.0 / i } else { 0.0 },
+                mass: m,
+                inv_mass: if m != 0.0 { 1.0 / m } else { 0.0 },
+                center: c + s,
+            }
+        }
+        // Sums the fixtures around their combined centroid with the parallel axis theorem
+        &Shape::Compound { ref fixtures } => {
+            let mut m = 0.0;
+            let mut i = 0.0;
+            let mut c = Vec2::new(0.0, 0.0);
+
+            let mut fixture_masses = Vec::with_capacity(fixtures.len());
+            for fixture in fixtures {
+                let mass_data = compute_mass(&fixture.shape, fixture.density);
+                let center = fixture.offset + Mat2::from_angle(fixture.rotation) * mass_data.center;
+
+                m += mass_data.mass;
+                c += center * mass_data.mass;
+                fixture_masses.push((mass_data, center));
             }
 
-            let m = density * area;
-            let i = i * density;
+            if m != 0.0 {
+                c /= m;
+            }
+
+            for &(ref mass_data, center) in &fixture_masses {
+                i += mass_data.moment_inertia + mass_data.mass * dist_sqr(center, c);
+            }
 
             MassData {
-                moment_inertia: m,
-                inv_inertia: if m != 0.0 { 1.0 / m } else { 0.0 },
-                mass: i,
-                inv_mass: if i != 0.0 { 1.0 / i } else { 0.0 },
+                moment_inertia: i,
+                inv_inertia: if i != 0.0 { 1.0 / i } else { 0.0 },
+                mass: m,
+                inv_mass: if m != 0.0 { 1.0 / m } else { 0.0 },
+                center: c,
+            }
+        }
+        // Edges and chains have no area, they are meant for static bodies
+        &Shape::Edge { .. } | &Shape::Chain { .. } => {
+            MassData {
+                moment_inertia: 0.0,
+                inv_inertia: 0.0,
+                mass: 0.0,
+                inv_mass: 0.0,
+                center: Vec2::new(0.0, 0.0),
             }
         }
+        // b2ComputeCapsuleMass, a box and the two half circles moved to its ends
+        &Shape::Capsule { half_height, radius } => {
+            let rr = radius * radius;
+            let length = 2.0 * half_height;
+
+            let circle_mass = PI * rr * density;
+            let box_mass = 2.0 * radius * length * density;
+            let m = circle_mass + box_mass;
+
+            let lc = 4.0 * radius / (3.0 * PI);
+            let circle_inertia = circle_mass * (0.5 * rr + half_height * half_height + 2.0 * half_height * lc);
+            let box_inertia = box_mass * (4.0 * rr + length * length) / 12.0;
+            let i = circle_inertia + box_inertia;
+
+            MassData {
+                moment_inertia: i,
+                inv_inertia: if i != 0.0 { 1.0 / i } else { 0.0 },
+                mass: m,
+                inv_mass: if m != 0.0 { 1.0 / m } else { 0.0 },
+                center: Vec2::new(0.0, 0.0),
+            }
+        }
+    }
+}
+
+// Closest point on a convex polygon to p, p itself when it's inside
+fn closest_point_polygon(p: Vec2, vertices: &[PolygonShapeVertex]) -> Vec2 {
+    if vertices.iter().all(|v| dot(v.normal, p - v.position) <= 0.0) {
+        return p
+    }
+
+    let mut closest = vertices[0].position;
+    for i in 0..vertices.len() {
+        let q = closest_point_segment(p, vertices[i].position, vertices[(i + 1) % vertices.len()].position);
+        if dist_sqr(p, q) < dist_sqr(p, closest) {
+            closest = q;
+        }
     }
+    closest
+}
+
+// b2CircleShape::RayCast in the circle's space, d is the full ray
+fn ray_cast_circle(p1: Vec2, d: Vec2, center: Vec2, radius: Real) -> Option<(Real, Vec2)> {
+    let s = p1 - center;
+    let b = dot(s, s) - radius * radius;
+    let c = dot(s, d);
+    let rr = dot(d, d);
+    let sigma = c * c - rr * b;
+    if sigma < 0.0 || rr < EPSILON {
+        return None
+    }
+
+    let a = -(c + sigma.sqrt());
+    if a < 0.0 || a > rr {
+        return None
+    }
+
+    let fraction = a / rr;
+    Some((fraction, (s + d * fraction).normalize()))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct Transform {
+    pub position: Vec2,
+    pub orient: Rad<Real>,
+}
+
+impl Transform {
+    // Linear interpolation from self (alpha = 0) to other (alpha = 1)
+    pub fn lerp(&self, other: &Transform, alpha: Real) -> Transform {
+        Transform {
+            position: self.position + (other.position - self.position) * alpha,
+            orient: Rad(self.orient.0 + (other.orient.0 - self.orient.0) * alpha),
+        }
+    }
+
+    pub fn rotation(&self) -> Mat2 {
+        Mat2::from_angle(self.orient)
+    }
+
+    pub fn world_point(&self, local_point: Vec2) -> Vec2 {
+        self.rotation() * local_point + self.position
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum BodyType {
+    // Never moves
+    Static,
+    // Moves by its velocity only, ignoring forces and contacts, and pushes dynamic bodies
+    Kinematic,
+    // Fully simulated
+    Dynamic,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Body {
     pub shape: Shape,
+    pub body_type: BodyType,
+    pub density: Real,
+    // Use the setters, these are folded into the mass data
+    pub fixed_rotation: bool,
+    pub lock_x: bool,
+    pub lock_y: bool,
 
+    // The center of mass, the shape is placed around it by local_center
     pub position: Vec2,
     pub orient: Rad<Real>,
     pub velocity: Vec2,
@@ -116,17 +594,26 @@ pub struct Body {
     pub inv_inertia: Real,
     pub mass: Real,
     pub inv_mass: Real,
+    // Center of mass in the shape's space
+    pub local_center: Vec2,
 }
 
 impl Body {
-    // Body::Body, Shape::Initialize
-    pub fn with_density(mut shape: Shape, position: Vec2, density: Real) -> Self {
-        let mass_data = compute_mass(&mut shape, density);
+    // Body::Body, Shape::Initialize. The shape's origin is put at position, which
+    // is the center of mass unless the shape is off center. Shapes without mass,
+    // like edges and chains, make a static body.
+    pub fn with_density(shape: Shape, position: Vec2, density: Real) -> Self {
+        let mass_data = compute_mass(&shape, density);
 
         Body {
             shape: shape,
+            body_type: if mass_data.mass > 0.0 { BodyType::Dynamic } else { BodyType::Static },
+            density: density,
+            fixed_rotation: false,
+            lock_x: false,
+            lock_y: false,
 
-            position: position,
+            position: position + mass_data.center,
             velocity: Vec2::new(0.0, 0.0),
             angular_velocity: 0.0,
             torque: 0.0,
@@ -140,6 +627,7 @@ impl Body {
             inv_inertia: mass_data.inv_inertia,
             mass: mass_data.mass,
             inv_mass: mass_data.inv_mass,
+            local_center: mass_data.center,
         }
     }
 
@@ -153,18 +641,189 @@ impl Body {
         self.force += force;
     }
 
+    // Applies a force at a point in world space, which also adds torque when
+    // the point is not the center of mass
+    pub fn apply_force_at_point(&mut self, force: Vec2, world_point: Vec2) {
+        self.force += force;
+        self.torque += cross_vectors(world_point - self.position, force);
+    }
+
+    pub fn apply_torque(&mut self, torque: Real) {
+        self.torque += torque;
+    }
+
     // Body::ApplyImpulse
     pub fn apply_impulse(&mut self, impulse: Vec2, contact_vector: Vec2) {
-        self.velocity += self.inv_mass * impulse;
+        self.velocity += self.constrain_translation(self.inv_mass * impulse);
         self.angular_velocity += self.inv_inertia * cross_vectors(contact_vector, impulse);
     }
 
+    // Same as apply_impulse, with the contact point in world space
+    pub fn apply_impulse_at_point(&mut self, impulse: Vec2, world_point: Vec2) {
+        let contact_vector = world_point - self.position;
+        self.apply_impulse(impulse, contact_vector);
+    }
+
+    pub fn apply_angular_impulse(&mut self, impulse: Real) {
+        self.angular_velocity += self.inv_inertia * impulse;
+    }
+
+    // Velocity of the body at a point in world space
+    pub fn velocity_at_point(&self, world_point: Vec2) -> Vec2 {
+        self.velocity + cross_real_vector(self.angular_velocity, world_point - self.position)
+    }
+
     // Body::SetStatic
     pub fn set_static(&mut self) {
-        self.moment_inertia = 0.0;
-        self.inv_inertia = 0.0;
-        self.mass = 0.0;
-        self.inv_mass = 0.0;
+        self.set_body_type(BodyType::Static);
+    }
+
+    pub fn set_kinematic(&mut self) {
+        self.set_body_type(BodyType::Kinematic);
+    }
+
+    pub fn set_dynamic(&mut self) {
+        self.set_body_type(BodyType::Dynamic);
+    }
+
+    // Static and kinematic bodies have infinite mass, dynamic bodies get their
+    // mass back from the shape and density
+    pub fn set_body_type(&mut self, body_type: BodyType) {
+        self.body_type = body_type;
+        self.reset_mass_data();
+
+        if body_type == BodyType::Static {
+            self.velocity = Vec2::new(0.0, 0.0);
+            self.angular_velocity = 0.0;
+        }
+    }
+
+    // Body::SetFixedRotation. The mass is recomputed from the shape through
+    // reset_mass_data, so a mass set with set_mass_data has to be set again after.
+    pub fn set_fixed_rotation(&mut self, fixed_rotation: bool) {
+        self.fixed_rotation = fixed_rotation;
+        self.reset_mass_data();
+
+        if fixed_rotation {
+            self.angular_velocity = 0.0;
+        }
+    }
+
+    // Locked axes keep their world space translation, the solver can't move the body along them
+    pub fn set_locked_axes(&mut self, lock_x: bool, lock_y: bool) {
+        self.lock_x = lock_x;
+        self.lock_y = lock_y;
+        self.velocity = self.constrain_translation(self.velocity);
+    }
+
+    // Zeroes the components of a translation or velocity along the locked axes
+    pub fn constrain_translation(&self, v: Vec2) -> Vec2 {
+        Vec2::new(
+            if self.lock_x { 0.0 } else { v.x },
+            if self.lock_y { 0.0 } else { v.y },
+        )
+    }
+
+    // Inverse mass along x and y, zero along the locked axes
+    pub fn inv_mass_axes(&self) -> Vec2 {
+        self.constrain_translation(Vec2::new(self.inv_mass, self.inv_mass))
+    }
+
+    // Inverse mass seen by an impulse along direction, which must be normalized
+    pub fn inv_mass_along(&self, direction: Vec2) -> Real {
+        let d = self.constrain_translation(direction);
+        self.inv_mass * (d.x * d.x + d.y * d.y)
+    }
+
+    // Body::ResetMassData, recomputes the mass data from the shape and density
+    pub fn reset_mass_data(&mut self) {
+        let mass_data = compute_mass(&self.shape, self.density);
+        let mass_data = match self.body_type {
+            BodyType::Static | BodyType::Kinematic => MassData::new(0.0, 0.0, mass_data.center),
+            BodyType::Dynamic => mass_data,
+        };
+        self.set_mass_data(mass_data);
+    }
+
+    pub fn mass_data(&self) -> MassData {
+        MassData {
+            moment_inertia: self.moment_inertia,
+            inv_inertia: self.inv_inertia,
+            mass: self.mass,
+            inv_mass: self.inv_mass,
+            center: self.local_center,
+        }
+    }
+
+    // Body::SetMassData, overrides the mass computed from the shape until the mass
+    // data is reset, which changing the body type or fixed rotation also does. The
+    // shape stays where it is, the center of mass moves to the new center.
+    pub fn set_mass_data(&mut self, mass_data: MassData) {
+        // Dynamic bodies always have mass, otherwise gravity would move them while
+        // contacts can't push them
+        let mass_data = if self.body_type == BodyType::Dynamic && mass_data.mass <= 0.0 {
+            MassData::new(1.0, mass_data.moment_inertia, mass_data.center)
+        } else {
+            mass_data
+        };
+
+        let origin = self.origin();
+        let old_center = self.position;
+        self.local_center = mass_data.center;
+        self.position = origin + self.rotation() * self.local_center;
+        // Keep the velocity of the points on the body
+        self.velocity += cross_real_vector(self.angular_velocity, self.position - old_center);
+
+        self.mass = mass_data.mass;
+        self.inv_mass = mass_data.inv_mass;
+
+        if self.fixed_rotation {
+            self.moment_inertia = 0.0;
+            self.inv_inertia = 0.0;
+        } else {
+            self.moment_inertia = mass_data.moment_inertia;
+            self.inv_inertia = mass_data.inv_inertia;
+        }
+    }
+
+    // Swaps the shape, keeping its origin in place, and recomputes the mass data for it
+    pub fn set_shape(&mut self, shape: Shape) {
+        self.shape = shape;
+        let orient = self.orient;
+        self.set_orient(orient);
+        self.reset_mass_data();
+    }
+
+    // Scales the shape around its origin and recomputes the mass data for it
+    pub fn scale(&mut self, factor: Real) {
+        self.shape.scale(factor);
+        self.reset_mass_data();
+    }
+
+    // Sets the density and recomputes the mass data from it
+    pub fn set_density(&mut self, density: Real) {
+        self.density = density;
+        self.reset_mass_data();
+    }
+
+    // Position of the shape's origin in world space
+    pub fn origin(&self) -> Vec2 {
+        self.shape_to_world(Vec2::new(0.0, 0.0))
+    }
+
+    // Moves the body so the shape's origin is at origin
+    pub fn set_origin(&mut self, origin: Vec2) {
+        self.position = origin + self.rotation() * self.local_center;
+    }
+
+    // b2Body::SetTransform, turns the body around the shape's origin instead of the center of mass
+    pub fn set_transform<T: Into<Rad<Real>>>(&mut self, origin: Vec2, radians: T) {
+        self.set_orient(radians);
+        self.set_origin(origin);
+    }
+
+    pub fn is_dynamic(&self) -> bool {
+        self.body_type == BodyType::Dynamic
     }
 
     // Body::SetOrient, Circle::SetOrient
@@ -173,7 +832,8 @@ impl Body {
         self.orient = radians;
 
         match self.shape {
-            Shape::Circle{ .. } => (),
+            Shape::Circle{ .. } | Shape::Capsule { .. } | Shape::Edge { .. } | Shape::Chain { .. } |
+            Shape::Compound { .. } => (),
             Shape::Polygon{ ref mut orientation, .. } => {
                 fn set(radians: Rad<Real>) -> Mat2
                 {
@@ -188,22 +848,346 @@ impl Body {
         }
     }
 
+    pub fn rotation(&self) -> Mat2 {
+        Mat2::from_angle(self.orient)
+    }
+
+    pub fn transform(&self) -> Transform {
+        Transform {
+            position: self.position,
+            orient: self.orient,
+        }
+    }
+
+    // Transforms a point in body space to world space
+    pub fn world_point(&self, local_point: Vec2) -> Vec2 {
+        self.rotation() * local_point + self.position
+    }
+
+    // Transforms a point in world space to body space
+    pub fn local_point(&self, world_point: Vec2) -> Vec2 {
+        self.rotation().transpose() * (world_point - self.position)
+    }
+
+    // Transforms a point in the shape's space, which is body space moved by local_center, to world space
+    pub fn shape_to_world(&self, shape_point: Vec2) -> Vec2 {
+        self.world_point(shape_point - self.local_center)
+    }
+
+    // Transforms a point in world space to the shape's space
+    pub fn world_to_shape(&self, world_point: Vec2) -> Vec2 {
+        self.local_point(world_point) + self.local_center
+    }
+
+    pub fn material(&self) -> Material {
+        Material {
+            static_friction: self.static_friction,
+            dynamic_friction: self.dynamic_friction,
+            restitution: self.restitution,
+        }
+    }
+
+    // The fixtures of a compound body as bodies of their own, moving with this one.
+    // Other shapes are their own single fixture.
+    pub fn fixture_bodies(&self) -> Vec<Body> {
+        let fixtures = match self.shape {
+            Shape::Compound { ref fixtures } => fixtures,
+            _ => return vec![self.clone()],
+        };
+
+        fixtures.iter().map(|fixture| {
+            let mut body = Body::with_density(fixture.shape.clone(), Vec2::new(0.0, 0.0), fixture.density);
+            body.set_transform(self.shape_to_world(fixture.offset), self.orient + fixture.rotation);
+            body.body_type = self.body_type;
+            body.velocity = self.velocity_at_point(body.position);
+            body.angular_velocity = self.angular_velocity;
+            body.static_friction = fixture.material.static_friction;
+            body.dynamic_friction = fixture.material.dynamic_friction;
+            body.restitution = fixture.material.restitution;
+            body
+        }).collect()
+    }
+
+    // b2Shape::TestPoint
+    pub fn contains_point(&self, world_point: Vec2) -> bool {
+        let p = self.world_to_shape(world_point);
+
+        match self.shape {
+            Shape::Circle { radius } => p.x * p.x + p.y * p.y <= radius * radius,
+            Shape::Polygon { ref vertices, radius, .. } => {
+                dist_sqr(p, closest_point_polygon(p, vertices)) <= radius * radius
+            }
+            Shape::Capsule { half_height, radius } => {
+                let q = closest_point_segment(p, Vec2::new(0.0, -half_height), Vec2::new(0.0, half_height));
+                (p - q).magnitude2() <= radius * radius
+            }
+            Shape::Edge { .. } | Shape::Chain { .. } => false,
+            Shape::Compound { .. } => self.fixture_bodies().iter().any(|body| body.contains_point(world_point)),
+        }
+    }
+
+    // b2Shape::RayCast, returns the fraction along p1 -> p2 and the world normal of
+    // the first hit. Rays starting inside a polygon don't hit it.
+    pub fn ray_cast(&self, world_p1: Vec2, world_p2: Vec2) -> Option<(Real, Vec2)> {
+        let p1 = self.world_to_shape(world_p1);
+        let p2 = self.world_to_shape(world_p2);
+        let d = p2 - p1;
+
+        match self.shape {
+            Shape::Compound { .. } => {
+                self.fixture_bodies().iter()
+                    .filter_map(|body| body.ray_cast(world_p1, world_p2))
+                    .fold(None, |closest: Option<(Real, Vec2)>, hit| match closest {
+                        Some(closest) if closest.0 <= hit.0 => Some(closest),
+                        _ => Some(hit),
+                    })
+            }
+            Shape::Circle { radius } => {
+                ray_cast_circle(p1, d, Vec2::new(0.0, 0.0), radius)
+                    .map(|(fraction, normal)| (fraction, self.rotation() * normal))
+            }
+            Shape::Capsule { half_height, radius } => {
+                let (a, b) = (Vec2::new(0.0, -half_height), Vec2::new(0.0, half_height));
+                if (p1 - closest_point_segment(p1, a, b)).magnitude2() <= radius * radius {
+                    return None
+                }
+
+                // The end caps and the two sides
+                let mut hits = vec![
+                    ray_cast_circle(p1, d, a, radius),
+                    ray_cast_circle(p1, d, b, radius),
+                ];
+                for &side in &[-radius, radius] {
+                    if d.x * side < 0.0 {
+                        let fraction = (side - p1.x) / d.x;
+                        let y = p1.y + d.y * fraction;
+                        if fraction >= 0.0 && fraction <= 1.0 && y.abs() <= half_height {
+                            hits.push(Some((fraction, Vec2::new(side.signum(), 0.0))));
+                        }
+                    }
+                }
+
+                hits.into_iter()
+                    .filter_map(|hit| hit)
+                    .fold(None, |closest: Option<(Real, Vec2)>, hit| match closest {
+                        Some(closest) if closest.0 <= hit.0 => Some(closest),
+                        _ => Some(hit),
+                    })
+                    .map(|(fraction, normal)| (fraction, self.rotation() * normal))
+            }
+            Shape::Polygon { ref vertices, radius, .. } if radius > 0.0 => {
+                if dist_sqr(p1, closest_point_polygon(p1, vertices)) <= radius * radius {
+                    return None
+                }
+
+                // The corners and the sides moved out by radius
+                let mut hits = vertices.iter()
+                    .map(|v| ray_cast_circle(p1, d, v.position, radius))
+                    .collect::<Vec<_>>();
+                for (i, v) in vertices.iter().enumerate() {
+                    let denominator = dot(v.normal, d);
+                    if denominator >= 0.0 {
+                        continue
+                    }
+
+                    let a = v.position + v.normal * radius;
+                    let e = vertices[(i + 1) % vertices.len()].position - v.position;
+                    let fraction = dot(v.normal, a - p1) / denominator;
+                    let s = dot(p1 + d * fraction - a, e) / dot(e, e);
+                    if fraction >= 0.0 && fraction <= 1.0 && s >= 0.0 && s <= 1.0 {
+                        hits.push(Some((fraction, v.normal)));
+                    }
+                }
+
+                hits.into_iter()
+                    .filter_map(|hit| hit)
+                    .fold(None, |closest: Option<(Real, Vec2)>, hit| match closest {
+                        Some(closest) if closest.0 <= hit.0 => Some(closest),
+                        _ => Some(hit),
+                    })
+                    .map(|(fraction, normal)| (fraction, self.rotation() * normal))
+            }
+            Shape::Polygon { ref vertices, .. } => {
+                let (mut lower, mut upper) = (0.0, 1.0);
+                let mut normal = None;
+
+                for v in vertices {
+                    let numerator = dot(v.normal, v.position - p1);
+                    let denominator = dot(v.normal, d);
+
+                    if denominator == 0.0 {
+                        if numerator < 0.0 {
+                            return None
+                        }
+                    } else if denominator < 0.0 && numerator < lower * denominator {
+                        lower = numerator / denominator;
+                        normal = Some(v.normal);
+                    } else if denominator > 0.0 && numerator < upper * denominator {
+                        upper = numerator / denominator;
+                    }
+
+                    if upper < lower {
+                        return None
+                    }
+                }
+
+                normal.map(|normal| (lower, self.rotation() * normal))
+            }
+            // b2EdgeShape::RayCast for every segment, one sided segments are only hit from the front
+            Shape::Edge { .. } | Shape::Chain { .. } => {
+                let mut closest: Option<(Real, Vec2)> = None;
+
+                for edge in self.shape.edges() {
+                    let e = edge.vertex2 - edge.vertex1;
+                    let mut normal = Vec2::new(e.y, -e.x).normalize();
+
+                    let numerator = dot(normal, edge.vertex1 - p1);
+                    if edge.one_sided && numerator > 0.0 {
+                        continue
+                    }
+                    let denominator = dot(normal, d);
+                    if denominator == 0.0 {
+                        continue
+                    }
+
+                    let t = numerator / denominator;
+                    if t < 0.0 || t > 1.0 || closest.map_or(false, |(fraction, _)| fraction <= t) {
+                        continue
+                    }
+
+                    let q = p1 + d * t;
+                    let s = dot(q - edge.vertex1, e) / dot(e, e);
+                    if s < 0.0 || s > 1.0 {
+                        continue
+                    }
+
+                    if numerator > 0.0 {
+                        normal = -normal;
+                    }
+                    closest = Some((t, normal));
+                }
+
+                closest.map(|(fraction, normal)| (fraction, self.rotation() * normal))
+            }
+        }
+    }
+
+    // Closest point on the body to a point in world space, the point itself when it's inside
+    pub fn closest_point(&self, world_point: Vec2) -> Vec2 {
+        let p = self.world_to_shape(world_point);
+
+        let closest = match self.shape {
+            Shape::Circle { radius } => {
+                if dot(p, p) <= radius * radius {
+                    return world_point
+                }
+                p.normalize() * radius
+            }
+            Shape::Polygon { ref vertices, radius, .. } => {
+                let q = closest_point_polygon(p, vertices);
+                if dist_sqr(p, q) <= radius * radius {
+                    return world_point
+                }
+                q + (p - q).normalize() * radius
+            }
+            Shape::Capsule { half_height, radius } => {
+                let q = closest_point_segment(p, Vec2::new(0.0, -half_height), Vec2::new(0.0, half_height));
+                if (p - q).magnitude2() <= radius * radius {
+                    return world_point
+                }
+                q + (p - q).normalize() * radius
+            }
+            Shape::Edge { .. } | Shape::Chain { .. } => {
+                let mut closest: Option<Vec2> = None;
+                for edge in self.shape.edges() {
+                    let q = closest_point_segment(p, edge.vertex1, edge.vertex2);
+                    if closest.map_or(true, |closest| (p - q).magnitude2() < (p - closest).magnitude2()) {
+                        closest = Some(q);
+                    }
+                }
+                match closest {
+                    Some(closest) => closest,
+                    None => return self.position,
+                }
+            }
+            Shape::Compound { .. } => {
+                let mut closest: Option<Vec2> = None;
+                for body in self.fixture_bodies() {
+                    let q = body.closest_point(world_point);
+                    if closest.map_or(true, |closest| dist_sqr(q, world_point) < dist_sqr(closest, world_point)) {
+                        closest = Some(q);
+                    }
+                }
+                return closest.unwrap_or(self.position)
+            }
+        };
+
+        self.shape_to_world(closest)
+    }
+
+    // Width of the body seen from a direction
+    pub fn projected_width(&self, direction: Vec2) -> Real {
+        match self.shape {
+            Shape::Circle { radius } => 2.0 * radius,
+            Shape::Polygon { .. } | Shape::Edge { .. } | Shape::Chain { .. } | Shape::Compound { .. } => {
+                let tangent = self.rotation().transpose() * Vec2::new(-direction.y, direction.x);
+                let (mut min, mut max) = (::std::f32::INFINITY, ::std::f32::NEG_INFINITY);
+                for v in self.shape.outline() {
+                    let projection = dot(v, tangent);
+                    min = min.min(projection);
+                    max = max.max(projection);
+                }
+                max - min
+            }
+            Shape::Capsule { half_height, radius } => {
+                let tangent = self.rotation().transpose() * Vec2::new(-direction.y, direction.x);
+                2.0 * radius + 2.0 * half_height * tangent.y.abs()
+            }
+        }
+    }
+
+    // b2Shape::ComputeSubmergedArea, returns the area and centroid of the part of
+    // the body inside a convex counter clockwise polygon. Curved shapes are clipped
+    // as their outline and scaled back to their real area.
+    pub fn submerged_area(&self, fluid: &[Vec2]) -> (Real, Vec2) {
+        if let Shape::Compound { .. } = self.shape {
+            let (mut area, mut centroid) = (0.0, Vec2::new(0.0, 0.0));
+            for body in self.fixture_bodies() {
+                let (fixture_area, fixture_centroid) = body.submerged_area(fluid);
+                area += fixture_area;
+                centroid += fixture_centroid * fixture_area;
+            }
+            return (area, if area > 0.0 { centroid / area } else { self.position })
+        }
+
+        if self.shape.area() <= 0.0 {
+            return (0.0, self.position)
+        }
+
+        let points = self.shape.outline().into_iter().map(|p| self.shape_to_world(p)).collect::<Vec<_>>();
+        let scale = self.shape.area() / polygon_area_centroid(&points).0;
+
+        let (area, centroid) = polygon_area_centroid(&clip_polygon(&points, fluid));
+        (area * scale, centroid)
+    }
+
     // IntegrateForces
     pub fn integrate_forces(&mut self, delta: Real) {
-        if self.inv_mass == 0.0 {
+        if self.body_type != BodyType::Dynamic {
             return
         }
         let safe_gravity = [GRAVITY[0], GRAVITY[1]];
-        self.velocity += (self.force * self.inv_mass + Vec2::from(safe_gravity)) * (delta / 2.0);
+        let acceleration = self.force * self.inv_mass + Vec2::from(safe_gravity);
+        self.velocity += self.constrain_translation(acceleration * (delta / 2.0));
         self.angular_velocity += self.torque * self.inv_inertia * (delta / 2.0);
     }
 
     // IntegrateVelocity
     pub fn integrate_velocity(&mut self, delta: Real) {
-        if self.inv_mass == 0.0 {
+        if self.body_type == BodyType::Static {
             return
         }
-        self.position += self.velocity * delta;
+        self.position += self.constrain_translation(self.velocity * delta);
         let orient = {
             self.orient = Rad(self.orient.0 + self.angular_velocity * delta);
             self.orient
@@ -212,4 +1196,100 @@ impl Body {
 
         self.integrate_forces(delta);
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::operations::float_cmp;
+
+    // Counter clockwise corners with unit normals pointing out of each face
+    fn assert_valid_polygon(shape: &Shape) -> Vec<Vec2> {
+        let vertices = match shape {
+            &Shape::Polygon { ref vertices, .. } => vertices,
+            _ => panic!("not a polygon"),
+        };
+        let points = vertices.iter().map(|v| v.position).collect::<Vec<_>>();
+        let n = points.len();
+        assert!(polygon_area_centroid(&points).0 > 0.0);
+
+        for i in 0..n {
+            let (a, b, c) = (points[i], points[(i + 1) % n], points[(i + 2) % n]);
+            assert!(cross_vectors(b - a, c - b) > 0.0, "{:?} isn't counter clockwise", points);
+
+            let normal = vertices[i].normal;
+            assert!(float_cmp(normal.magnitude(), 1.0));
+            assert!(float_cmp(dot(normal, b - a), 0.0));
+            assert!(points.iter().all(|&p| dot(normal, p - a) <= EPSILON), "{:?} points inwards", normal);
+        }
+        points
+    }
+
+    #[test]
+    fn triangle_in_either_order() {
+        let (a, b, c) = (Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0));
+        let forward = assert_valid_polygon(&Shape::triangle(a, b, c));
+        let backward = assert_valid_polygon(&Shape::triangle(a, c, b));
+        assert_eq!(forward, backward);
+        assert!(forward.contains(&a) && forward.contains(&b) && forward.contains(&c));
+    }
+
+    #[test]
+    fn regular_polygon_corners() {
+        for sides in 3..9 {
+            let points = assert_valid_polygon(&Shape::regular_polygon(sides, 10.0));
+            assert_eq!(points.len(), sides);
+            assert!(points.iter().all(|p| float_cmp(p.magnitude(), 10.0)));
+        }
+    }
+
+    #[test]
+    fn ellipse_corners() {
+        let points = assert_valid_polygon(&Shape::ellipse(Vec2::new(20.0, 10.0), 16));
+        assert_eq!(points.len(), 16);
+        assert!(points.iter().all(|p| float_cmp((p.x / 20.0).powi(2) + (p.y / 10.0).powi(2), 1.0)));
+    }
+
+    #[test]
+    fn offset_rect_corners() {
+        let center = Vec2::new(30.0, -5.0);
+        let points = assert_valid_polygon(&Shape::offset_rect(Vec2::new(20.0, 10.0), center, Rad(0.4)));
+        assert_eq!(points.len(), 4);
+
+        let (area, centroid) = polygon_area_centroid(&points);
+        assert!((area - 800.0).abs() < 0.01);
+        assert!((centroid - center).magnitude() < 0.001);
+        // The bottom edge of the rect, turned by the angle
+        let bottom = points[1] - points[0];
+        assert!((bottom - Vec2::new(0.4f32.cos(), 0.4f32.sin()) * 40.0).magnitude() < 0.001, "{:?}", bottom);
+    }
+
+    #[test]
+    #[should_panic]
+    fn degenerate_triangle() {
+        Shape::triangle(Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0), Vec2::new(20.0, 20.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn regular_polygon_with_two_sides() {
+        Shape::regular_polygon(2, 10.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn flat_ellipse() {
+        Shape::ellipse(Vec2::new(20.0, 0.0), 16);
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_offset_rect() {
+        Shape::offset_rect(Vec2::new(0.0, 10.0), Vec2::new(0.0, 0.0), Rad(0.0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn clockwise_polygon() {
+        Shape::polygon(&[Vec2::new(0.0, 0.0), Vec2::new(0.0, 10.0), Vec2::new(10.0, 0.0)]);
+    }
+}
@@ -1,16 +1,21 @@
 use super::types::{Vec2, Real, Mat2};
 use super::scene::BodyIndex;
 use super::Body;
-use super::scene::{GRAVITY, EPSILON};
-use super::body::PolygonShapeVertex;
-use super::operations::{cross_real_vector, len_sqr, dist_sqr, float_cmp};
+use super::scene::EPSILON;
+use super::body::{PolygonShapeVertex, EdgeSegment, Material};
+use super::operations::{cross_real_vector, cross_vectors, len_sqr, dist_sqr, float_cmp, closest_point_segment};
 use cgmath::{dot, Matrix, InnerSpace};
 
 pub struct ManifoldData {
     pub pair: (BodyIndex, BodyIndex),
+    // The deepest of penetrations
     pub penetration: Real,
     pub normal: Vec2,
     pub contacts: Vec<Vec2>,
+    // Penetration at each contact
+    pub penetrations: Vec<Real>,
+    // Used instead of the materials of the bodies, for the fixtures of compound bodies
+    pub materials: Option<(Material, Material)>,
 }
 
 pub struct Manifold {
@@ -24,13 +29,27 @@ pub struct Manifold {
 }
 
 impl ManifoldData {
-    pub fn initialize(&self, delta: Real, body_a: &Body, body_b: &Body) -> Manifold {
+    // A manifold from its contacts and the penetration at each of them
+    fn from_points(pair: (BodyIndex, BodyIndex), normal: Vec2, points: Vec<(Vec2, Real)>) -> Self {
+        ManifoldData {
+            pair: pair,
+            penetration: points.iter().map(|&(_, penetration)| penetration).fold(0.0, Real::max),
+            normal: normal,
+            contacts: points.iter().map(|&(p, _)| p).collect(),
+            penetrations: points.iter().map(|&(_, penetration)| penetration).collect(),
+            materials: None,
+        }
+    }
+
+    pub fn initialize(&self, restitution_threshold: Real, body_a: &Body, body_b: &Body) -> Manifold {
+        let (material_a, material_b) = self.materials.unwrap_or((body_a.material(), body_b.material()));
+
         // Calculate average restitution
-        let mut e = body_a.restitution.min(body_b.restitution);
+        let mut e = material_a.restitution.min(material_b.restitution);
 
         // Calculate static and dynamic friction
-        let sf = body_a.static_friction.powi(2).sqrt();
-        let df = body_a.dynamic_friction.powi(2).sqrt();
+        let sf = material_a.static_friction.powi(2).sqrt();
+        let df = material_a.dynamic_friction.powi(2).sqrt();
 
         for contact in &self.contacts {
             let ra = contact - body_a.position;
@@ -39,7 +58,7 @@ impl ManifoldData {
             let rv = body_b.velocity + cross_real_vector(body_b.angular_velocity, rb) -
                      body_a.velocity - cross_real_vector(body_a.angular_velocity, ra);
 
-            if len_sqr(rv) < len_sqr(Vec2::new(GRAVITY[0] * delta, GRAVITY[1] * delta)) + EPSILON {
+            if len_sqr(rv) < restitution_threshold.powi(2) + EPSILON {
                 e = 0.0;
             }
         }
@@ -63,7 +82,8 @@ pub fn circle_circle(
     -> Option<ManifoldData> {
     // Calculate translational vePolygonShapeVertexctor, which is normal
 
-    let normal = body_b.position - body_a.position;
+    let center_a = body_a.origin();
+    let normal = body_b.origin() - center_a;
     let dist_sqr = len_sqr(normal);
     let radius = radius_a + radius_b;
 
@@ -77,7 +97,9 @@ pub fn circle_circle(
             pair: (i_a, i_b),
             penetration: radius_a,
             normal: Vec2::new(1.0, 0.0),
-            contacts: vec![body_a.position]
+            contacts: vec![center_a],
+            penetrations: vec![radius_a],
+            materials: None,
         })
     } else {
         let normal_over_distance = normal / distance;
@@ -85,28 +107,31 @@ pub fn circle_circle(
             pair: (i_a, i_b),
             penetration: radius - distance,
             normal: normal_over_distance,
-            contacts: vec![normal_over_distance * radius_a + body_a.position],
+            contacts: vec![normal_over_distance * radius_a + center_a],
+            penetrations: vec![radius - distance],
+            materials: None,
         })
     }
 }
 
 pub fn circle_polygon(
         (i_a, radius_a, body_a): (BodyIndex, Real, &Body), 
-        (i_b, orientation_b, vertices_b, body_b): (BodyIndex, &Mat2, &Vec<PolygonShapeVertex>, &Body)) 
+        (i_b, orientation_b, vertices_b, radius_b, body_b): (BodyIndex, &Mat2, &Vec<PolygonShapeVertex>, Real, &Body)) 
     -> Option<ManifoldData> {
 
-    let pos_a = body_a.position;
-    let pos_b = body_b.position;
+    let pos_a = body_a.origin();
+
+    // A rounded polygon is its core polygon inflated by radius_b
+    let radius = radius_a + radius_b;
 
-    let mut center = pos_a;
-    center = orientation_b.transpose() * (center - pos_b);
+    let center = body_b.world_to_shape(pos_a);
 
     let mut separation = ::std::f32::MIN;
     let mut face_normal = 0;
     for (i, vertex) in vertices_b.iter().enumerate() {
         let s = dot(vertex.normal, center - vertex.position);
 
-        if s > radius_a {
+        if s > radius {
             return None
         }
 
@@ -125,18 +150,20 @@ pub fn circle_polygon(
 
         return Some(ManifoldData {
             pair: (i_a, i_b),
-            penetration: radius_a,
+            penetration: radius,
             normal: normal,
             contacts: vec![normal * radius_a + pos_a],
+            penetrations: vec![radius],
+            materials: None,
         })
     }
 
     let dot1 = dot(center - v1.position, v2.position - v1.position);
     let dot2 = dot(center - v2.position, v1.position - v2.position);
-    let penetration = radius_a - separation;
+    let penetration = radius - separation;
 
     if dot1 <= 0.0 {
-        if dist_sqr(center, v1.position) > radius_a.powi(2) {
+        if dist_sqr(center, v1.position) > radius.powi(2) {
             return None
         }
 
@@ -145,16 +172,18 @@ pub fn circle_polygon(
         if !float_cmp(n.magnitude(), 0.0) {
             n = n.normalize();
         }
-        v1.position = orientation_b * v1.position + pos_b;
+        v1.position = body_b.shape_to_world(v1.position) - n * radius_b;
 
         Some(ManifoldData {
             pair: (i_a, i_b),
             penetration: penetration,
             normal: n,
-            contacts: vec![v1.position]
+            contacts: vec![v1.position],
+            penetrations: vec![penetration],
+            materials: None,
         })
     } else if dot2 <= 0.0 {
-        if dist_sqr(center, v2.position) > radius_a.powi(2) {
+        if dist_sqr(center, v2.position) > radius.powi(2) {
             return None
         }
 
@@ -164,18 +193,20 @@ pub fn circle_polygon(
             n = n.normalize();
         }
         
-        v2.position = orientation_b * v2.position + pos_b;
+        v2.position = body_b.shape_to_world(v2.position) - n * radius_b;
 
         Some(ManifoldData {
             pair: (i_a, i_b),
             penetration: penetration,
             normal: n,
-            contacts: vec![v2.position]
+            contacts: vec![v2.position],
+            penetrations: vec![penetration],
+            materials: None,
         })
     } else {
         let mut n = v1.normal;
 
-        if dot(center - v1.position, n) > radius_a {
+        if dot(center - v1.position, n) > radius {
             return None
         }
 
@@ -185,7 +216,682 @@ pub fn circle_polygon(
             pair: (i_a, i_b),
             penetration: penetration,
             normal: n,
-            contacts: vec![n * radius_a + pos_a]
+            contacts: vec![n * radius_a + pos_a],
+            penetrations: vec![penetration],
+            materials: None,
+        })
+    }
+}
+
+// Segments closer to parallel than this (sine of the angle) get two contacts
+const PARALLEL_TOLERANCE: Real = 0.005;
+
+fn clamp01(x: Real) -> Real {
+    x.max(0.0).min(1.0)
+}
+
+// Closest points between the segments p1 -> q1 and p2 -> q2,
+// Real-Time Collision Detection 5.1.9
+fn closest_points_segments(p1: Vec2, q1: Vec2, p2: Vec2, q2: Vec2) -> (Vec2, Vec2) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = dot(d1, d1);
+    let e = dot(d2, d2);
+    let f = dot(d2, r);
+
+    if a <= EPSILON && e <= EPSILON {
+        return (p1, p2)
+    }
+
+    let (s, t) = if a <= EPSILON {
+        (0.0, clamp01(f / e))
+    } else {
+        let c = dot(d1, r);
+        if e <= EPSILON {
+            (clamp01(-c / a), 0.0)
+        } else {
+            let b = dot(d1, d2);
+            let denom = a * e - b * b;
+            let s = if denom != 0.0 { clamp01((b * f - c * e) / denom) } else { 0.0 };
+            let t = (b * s + f) / e;
+
+            if t < 0.0 {
+                (clamp01(-c / a), 0.0)
+            } else if t > 1.0 {
+                (clamp01((b - c) / a), 1.0)
+            } else {
+                (s, t)
+            }
+        }
+    };
+
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+// Contact between two segments inflated by their radii, returns the normal from a to b
+// and the contacts on the surface of a with their penetration. Nearly parallel segments
+// get a contact at both ends of their overlap so they can rest on each other.
+fn segment_segment((a1, a2, radius_a): (Vec2, Vec2, Real), (b1, b2, radius_b): (Vec2, Vec2, Real))
+    -> Option<(Vec2, Vec<(Vec2, Real)>)> {
+
+    let radius = radius_a + radius_b;
+    let (pa, pb) = closest_points_segments(a1, a2, b1, b2);
+    let distance_sqr = dist_sqr(pa, pb);
+    if distance_sqr >= radius.powi(2) {
+        return None
+    }
+    let distance = distance_sqr.sqrt();
+
+    let axis_a = a2 - a1;
+    let axis_b = b2 - b1;
+    let normal = if distance > EPSILON {
+        (pb - pa) / distance
+    } else {
+        // The segments cross, push b out along the side of a it is on
+        let mut n = if len_sqr(axis_a) > EPSILON { cross_real_vector(1.0, axis_a).normalize() } else { Vec2::new(1.0, 0.0) };
+        if dot(n, (b1 + b2) - (a1 + a2)) < 0.0 {
+            n = -n;
+        }
+        n
+    };
+
+    let (length_a, length_b) = (axis_a.magnitude(), axis_b.magnitude());
+    if length_a > EPSILON && length_b > EPSILON &&
+       cross_vectors(axis_a, axis_b).abs() < PARALLEL_TOLERANCE * length_a * length_b {
+        let u = axis_a / length_a;
+        let (t1, t2) = (dot(b1 - a1, u), dot(b2 - a1, u));
+        let lower = t1.min(t2).max(0.0);
+        let upper = t1.max(t2).min(length_a);
+
+        if upper - lower > EPSILON {
+            let contacts = [lower, upper].iter()
+                .map(|&t| a1 + u * t)
+                .map(|p| (p, radius - dot(closest_point_segment(p, b1, b2) - p, normal)))
+                .filter(|&(_, penetration)| penetration > 0.0)
+                .map(|(p, penetration)| (p + normal * radius_a, penetration))
+                .collect::<Vec<_>>();
+
+            if contacts.len() == 2 {
+                return Some((normal, contacts))
+            }
+        }
+    }
+
+    Some((normal, vec![(pa + normal * radius_a, radius - distance)]))
+}
+
+// Whether the segment p1 -> p2 crosses a convex polygon, Cyrus-Beck clipping
+fn segment_intersects_polygon(p1: Vec2, p2: Vec2, vertices: &Vec<PolygonShapeVertex>) -> bool {
+    let d = p2 - p1;
+    let (mut lower, mut upper) = (0.0, 1.0);
+
+    for v in vertices {
+        let numerator = dot(v.normal, v.position - p1);
+        let denominator = dot(v.normal, d);
+
+        if denominator == 0.0 {
+            if numerator < 0.0 {
+                return false
+            }
+        } else if denominator < 0.0 {
+            lower = (numerator / denominator).max(lower);
+        } else {
+            upper = (numerator / denominator).min(upper);
+        }
+
+        if upper < lower {
+            return false
+        }
+    }
+
+    true
+}
+
+fn capsule_segment(half_height: Real, body: &Body) -> (Vec2, Vec2) {
+    (body.shape_to_world(Vec2::new(0.0, -half_height)), body.shape_to_world(Vec2::new(0.0, half_height)))
+}
+
+pub fn capsule_circle(
+        (i_a, half_height, radius_a, body_a): (BodyIndex, Real, Real, &Body),
+        (i_b, radius_b, body_b): (BodyIndex, Real, &Body))
+    -> Option<ManifoldData> {
+
+    let (a1, a2) = capsule_segment(half_height, body_a);
+    let center = body_b.origin();
+
+    segment_segment((a1, a2, radius_a), (center, center, radius_b)).map(|(normal, points)| {
+        ManifoldData::from_points((i_a, i_b), normal, points)
+    })
+}
+
+pub fn capsule_capsule(
+        (i_a, half_height_a, radius_a, body_a): (BodyIndex, Real, Real, &Body),
+        (i_b, half_height_b, radius_b, body_b): (BodyIndex, Real, Real, &Body))
+    -> Option<ManifoldData> {
+
+    let (a1, a2) = capsule_segment(half_height_a, body_a);
+    let (b1, b2) = capsule_segment(half_height_b, body_b);
+
+    segment_segment((a1, a2, radius_a), (b1, b2, radius_b)).map(|(normal, points)| {
+        ManifoldData::from_points((i_a, i_b), normal, points)
+    })
+}
+
+pub fn capsule_polygon(
+        (i_a, half_height, radius_a, body_a): (BodyIndex, Real, Real, &Body),
+        (i_b, orientation_b, vertices_b, radius_b, body_b): (BodyIndex, &Mat2, &Vec<PolygonShapeVertex>, Real, &Body))
+    -> Option<ManifoldData> {
+
+    // Work in the polygon's space
+    let (a1, a2) = capsule_segment(half_height, body_a);
+    let p1 = body_b.world_to_shape(a1);
+    let p2 = body_b.world_to_shape(a2);
+
+    let result = if segment_intersects_polygon(p1, p2, vertices_b) {
+        // Deep contact, push out along the polygon face with the least penetration
+        let mut separation = ::std::f32::MIN;
+        let mut face = 0;
+        for (i, v) in vertices_b.iter().enumerate() {
+            let s = dot(v.normal, p1 - v.position).min(dot(v.normal, p2 - v.position));
+            if s > separation {
+                separation = s;
+                face = i;
+            }
+        }
+
+        let v1 = vertices_b[face].position;
+        let v2 = vertices_b[(face + 1) % vertices_b.len()].position;
+        let n = vertices_b[face].normal;
+
+        // Clip the segment to the sides of the face
+        let e = v2 - v1;
+        let (t1, t2) = (dot(p1 - v1, e) / dot(e, e), dot(p2 - v1, e) / dot(e, e));
+        let mut contacts = [(t1, p1), (t2, p2)].iter()
+            .map(|&(t, p)| if (t < 0.0 || t > 1.0) && (t2 - t1).abs() > EPSILON {
+                p1 + (p2 - p1) * ((clamp01(t) - t1) / (t2 - t1))
+            } else {
+                p
+            })
+            .map(|p| (p, radius_a + radius_b - dot(n, p - v1)))
+            .filter(|&(_, penetration)| penetration > 0.0)
+            .map(|(p, penetration)| (p - n * radius_a, penetration))
+            .collect::<Vec<_>>();
+
+        if contacts.is_empty() {
+            let p = if dot(n, p1 - v1) < dot(n, p2 - v1) { p1 } else { p2 };
+            contacts.push((p - n * radius_a, radius_a + radius_b - separation));
+        }
+
+        Some((-n, contacts))
+    } else {
+        // Shallow contact with the closest edge, preferring the one with more contacts on ties
+        let deepest = |points: &[(Vec2, Real)]| points.iter().map(|&(_, penetration)| penetration).fold(0.0, Real::max);
+        let mut best: Option<(Vec2, Vec<(Vec2, Real)>)> = None;
+        for i in 0..vertices_b.len() {
+            let v1 = vertices_b[i].position;
+            let v2 = vertices_b[(i + 1) % vertices_b.len()].position;
+
+            if let Some(contact) = segment_segment((p1, p2, radius_a), (v1, v2, radius_b)) {
+                let better = match best {
+                    None => true,
+                    Some(ref best) => {
+                        let (depth, best_depth) = (deepest(&contact.1), deepest(&best.1));
+                        depth > best_depth + EPSILON ||
+                        (float_cmp(depth, best_depth) && contact.1.len() > best.1.len())
+                    }
+                };
+                if better {
+                    best = Some(contact);
+                }
+            }
+        }
+        best
+    };
+
+    result.map(|(normal, points)| {
+        let points = points.into_iter().map(|(p, penetration)| (body_b.shape_to_world(p), penetration)).collect();
+        ManifoldData::from_points((i_a, i_b), orientation_b * normal, points)
+    })
+}
+
+// Hysteresis between the edge and polygon axes, b2_relativeTol and b2_absoluteTol
+const RELATIVE_TOLERANCE: Real = 0.98;
+const ABSOLUTE_TOLERANCE: Real = 0.05;
+// Polygon normals this close to a neighbouring segment's normal belong to it
+const SIN_TOLERANCE: Real = 0.1;
+
+// Whether a point next to the start or end of a chain segment is handled by the neighbour
+fn in_previous_region(edge: &EdgeSegment, q: Vec2) -> bool {
+    edge.vertex0.map_or(false, |v0| dot(edge.vertex1 - v0, edge.vertex1 - q) > 0.0)
+}
+
+fn in_next_region(edge: &EdgeSegment, q: Vec2) -> bool {
+    edge.vertex3.map_or(false, |v3| dot(v3 - edge.vertex2, q - edge.vertex2) > 0.0)
+}
+
+// b2CollideEdgeAndCircle
+pub fn edge_circle(
+        (i_a, edge, body_a): (BodyIndex, &EdgeSegment, &Body),
+        (i_b, radius_b, body_b): (BodyIndex, Real, &Body))
+    -> Option<ManifoldData> {
+
+    let q = body_a.world_to_shape(body_b.origin());
+    let (a, b) = (edge.vertex1, edge.vertex2);
+    let e = b - a;
+    let n = Vec2::new(e.y, -e.x).normalize();
+
+    let offset = dot(n, q - a);
+    if edge.one_sided && offset < 0.0 {
+        return None
+    }
+
+    let u = dot(e, b - q);
+    let v = dot(e, q - a);
+
+    let (p, face_normal) = if v <= 0.0 {
+        if in_previous_region(edge, q) {
+            return None
+        }
+        (a, None)
+    } else if u <= 0.0 {
+        if in_next_region(edge, q) {
+            return None
+        }
+        (b, None)
+    } else {
+        ((a * u + b * v) / dot(e, e), Some(if offset < 0.0 { -n } else { n }))
+    };
+
+    let d = q - p;
+    if len_sqr(d) > radius_b.powi(2) {
+        return None
+    }
+    let distance = d.magnitude();
+    let normal = face_normal.unwrap_or(if distance > EPSILON { d / distance } else { n });
+
+    Some(ManifoldData {
+        pair: (i_a, i_b),
+        penetration: radius_b - distance,
+        normal: body_a.rotation() * normal,
+        contacts: vec![body_a.shape_to_world(p)],
+        penetrations: vec![radius_b - distance],
+        materials: None,
+    })
+}
+
+// b2ClipSegmentToLine, keeps the part of the segment behind the plane
+fn clip_segment_to_line(points: &[Vec2], normal: Vec2, offset: Real) -> Vec<Vec2> {
+    let (v0, v1) = (points[0], points[1]);
+    let d0 = dot(normal, v0) - offset;
+    let d1 = dot(normal, v1) - offset;
+
+    let mut output = Vec::with_capacity(2);
+    if d0 <= 0.0 {
+        output.push(v0);
+    }
+    if d1 <= 0.0 {
+        output.push(v1);
+    }
+    if d0 * d1 < 0.0 {
+        output.push(v0 + (v1 - v0) * (d0 / (d0 - d1)));
+    }
+    output
+}
+
+// b2CollideEdgeAndPolygon
+pub fn edge_polygon(
+        (i_a, edge, body_a): (BodyIndex, &EdgeSegment, &Body),
+        (i_b, vertices_b, radius_b, body_b): (BodyIndex, &Vec<PolygonShapeVertex>, Real, &Body))
+    -> Option<ManifoldData> {
+
+    // The polygon in the edge's space
+    let rotation = body_a.rotation().transpose() * body_b.rotation();
+    let polygon = vertices_b.iter()
+        .map(|v| (body_a.world_to_shape(body_b.shape_to_world(v.position)), rotation * v.normal))
+        .collect::<Vec<_>>();
+    let centroid = polygon.iter().fold(Vec2::new(0.0, 0.0), |c, &(p, _)| c + p) / polygon.len() as Real;
+
+    let (v1, v2) = (edge.vertex1, edge.vertex2);
+    let edge1 = (v2 - v1).normalize();
+    let normal1 = Vec2::new(edge1.y, -edge1.x);
+    if edge.one_sided && dot(normal1, centroid - v1) < 0.0 {
+        return None
+    }
+
+    // b2ComputeEdgeSeparation
+    let mut edge_axis = (::std::f32::MIN, normal1);
+    for &axis in &[normal1, -normal1] {
+        let s = polygon.iter().map(|&(p, _)| dot(axis, p - v1)).fold(::std::f32::MAX, Real::min);
+        if s > edge_axis.0 {
+            edge_axis = (s, axis);
+        }
+    }
+    if edge_axis.0 > radius_b {
+        return None
+    }
+
+    // b2ComputePolygonSeparation
+    let mut polygon_axis = (::std::f32::MIN, normal1, 0);
+    for (i, &(p, n)) in polygon.iter().enumerate() {
+        let s = dot(-n, p - v1).min(dot(-n, p - v2));
+        if s > polygon_axis.0 {
+            polygon_axis = (s, -n, i);
+        }
+    }
+    if polygon_axis.0 > radius_b {
+        return None
+    }
+
+    // None for the edge axis, the face index for a polygon axis
+    let mut primary = if polygon_axis.0 > RELATIVE_TOLERANCE * edge_axis.0 + ABSOLUTE_TOLERANCE {
+        (polygon_axis.1, Some(polygon_axis.2))
+    } else {
+        (edge_axis.1, None)
+    };
+
+    if edge.one_sided {
+        // Smooth collision, skip normals owned by the neighbours and snap to the
+        // edge normal on concave corners
+        let normal = primary.0;
+        if dot(normal, edge1) <= 0.0 {
+            if let Some(v0) = edge.vertex0 {
+                let edge0 = (v1 - v0).normalize();
+                let normal0 = Vec2::new(edge0.y, -edge0.x);
+                if cross_vectors(edge0, edge1) >= 0.0 {
+                    if cross_vectors(normal, normal0) > SIN_TOLERANCE {
+                        return None
+                    }
+                } else {
+                    primary = (edge_axis.1, None);
+                }
+            }
+        } else if let Some(v3) = edge.vertex3 {
+            let edge2 = (v3 - v2).normalize();
+            let normal2 = Vec2::new(edge2.y, -edge2.x);
+            if cross_vectors(edge1, edge2) >= 0.0 {
+                if cross_vectors(normal2, normal) > SIN_TOLERANCE {
+                    return None
+                }
+            } else {
+                primary = (edge_axis.1, None);
+            }
+        }
+    }
+
+    let (clip_points, ref_v1, ref_v2, ref_normal, side_normal, normal) = match primary {
+        (normal, None) => {
+            // The polygon face most anti-parallel to the edge normal
+            let mut incident = 0;
+            for i in 1..polygon.len() {
+                if dot(normal, polygon[i].1) < dot(normal, polygon[incident].1) {
+                    incident = i;
+                }
+            }
+            let next = (incident + 1) % polygon.len();
+
+            ([polygon[incident].0, polygon[next].0], v1, v2, normal, -edge1, normal)
+        }
+        (normal, Some(face)) => {
+            let next = (face + 1) % polygon.len();
+            let face_normal = polygon[face].1;
+
+            ([v2, v1], polygon[face].0, polygon[next].0, face_normal, Vec2::new(face_normal.y, -face_normal.x), normal)
+        }
+    };
+
+    let clipped = clip_segment_to_line(&clip_points, side_normal, dot(side_normal, ref_v1));
+    if clipped.len() < 2 {
+        return None
+    }
+    let clipped = clip_segment_to_line(&clipped, -side_normal, dot(-side_normal, ref_v2));
+    if clipped.len() < 2 {
+        return None
+    }
+
+    let points = clipped.into_iter()
+        .map(|p| (body_a.shape_to_world(p), radius_b - dot(ref_normal, p - ref_v1)))
+        .filter(|&(_, penetration)| penetration >= 0.0)
+        .collect::<Vec<_>>();
+
+    if points.is_empty() {
+        return None
+    }
+
+    Some(ManifoldData::from_points((i_a, i_b), body_a.rotation() * normal, points))
+}
+
+// A polygon's vertices and face normals in world space
+fn world_polygon(orientation: &Mat2, vertices: &Vec<PolygonShapeVertex>, body: &Body) -> Vec<(Vec2, Vec2)> {
+    vertices.iter().map(|v| (body.shape_to_world(v.position), orientation * v.normal)).collect()
+}
+
+// b2FindMaxSeparation, the face of a that b is furthest in front of
+fn max_separation(polygon_a: &[(Vec2, Vec2)], polygon_b: &[(Vec2, Vec2)]) -> (Real, usize) {
+    let mut best = (::std::f32::MIN, 0);
+    for (i, &(v, n)) in polygon_a.iter().enumerate() {
+        let s = polygon_b.iter().map(|&(p, _)| dot(n, p - v)).fold(::std::f32::MAX, Real::min);
+        if s > best.0 {
+            best = (s, i);
+        }
+    }
+    best
+}
+
+// b2CollidePolygons, the face with the largest separation is the reference face and the
+// incident face of the other polygon is clipped against its sides. The polygons are
+// inflated by their radii, the contacts sit halfway between the rounded surfaces.
+pub fn polygon_polygon(
+        (i_a, orientation_a, vertices_a, radius_a, body_a): (BodyIndex, &Mat2, &Vec<PolygonShapeVertex>, Real, &Body),
+        (i_b, orientation_b, vertices_b, radius_b, body_b): (BodyIndex, &Mat2, &Vec<PolygonShapeVertex>, Real, &Body))
+    -> Option<ManifoldData> {
+
+    let polygon_a = world_polygon(orientation_a, vertices_a, body_a);
+    let polygon_b = world_polygon(orientation_b, vertices_b, body_b);
+    let radius = radius_a + radius_b;
+
+    let (separation_a, face_a) = max_separation(&polygon_a, &polygon_b);
+    if separation_a > radius {
+        return None
+    }
+    let (separation_b, face_b) = max_separation(&polygon_b, &polygon_a);
+    if separation_b > radius {
+        return None
+    }
+
+    // Faces of a are preferred so the manifold doesn't flip between nearly equal axes
+    let (reference, incident, face, (radius_ref, radius_inc), flip) = if separation_b > separation_a + ABSOLUTE_TOLERANCE {
+        (&polygon_b, &polygon_a, face_b, (radius_b, radius_a), true)
+    } else {
+        (&polygon_a, &polygon_b, face_a, (radius_a, radius_b), false)
+    };
+
+    let (v1, ref_normal) = reference[face];
+    let v2 = reference[(face + 1) % reference.len()].0;
+    let tangent = (v2 - v1).normalize();
+
+    // b2FindIncidentEdge, the face most anti-parallel to the reference normal
+    let mut incident_face = 0;
+    for i in 1..incident.len() {
+        if dot(ref_normal, incident[i].1) < dot(ref_normal, incident[incident_face].1) {
+            incident_face = i;
+        }
+    }
+    let clip_points = [incident[incident_face].0, incident[(incident_face + 1) % incident.len()].0];
+
+    let clipped = clip_segment_to_line(&clip_points, -tangent, radius - dot(tangent, v1));
+    if clipped.len() < 2 {
+        return None
+    }
+    let clipped = clip_segment_to_line(&clipped, tangent, radius + dot(tangent, v2));
+    if clipped.len() < 2 {
+        return None
+    }
+
+    // b2WorldManifold::Initialize, the midpoint of the two surfaces
+    let points = clipped.into_iter()
+        .map(|p| {
+            let separation = dot(ref_normal, p - v1);
+            let surface_ref = p + ref_normal * (radius_ref - separation);
+            let surface_inc = p - ref_normal * radius_inc;
+            ((surface_ref + surface_inc) * 0.5, radius - separation)
         })
+        .filter(|&(_, penetration)| penetration >= 0.0)
+        .collect::<Vec<_>>();
+
+    if points.is_empty() {
+        return None
+    }
+
+    let normal = if flip { -ref_normal } else { ref_normal };
+    Some(ManifoldData::from_points((i_a, i_b), normal, points))
+}
+
+pub fn edge_capsule(
+        (i_a, edge, body_a): (BodyIndex, &EdgeSegment, &Body),
+        (i_b, half_height, radius_b, body_b): (BodyIndex, Real, Real, &Body))
+    -> Option<ManifoldData> {
+
+    let (b1, b2) = capsule_segment(half_height, body_b);
+    let (b1, b2) = (body_a.world_to_shape(b1), body_a.world_to_shape(b2));
+    let (v1, v2) = (edge.vertex1, edge.vertex2);
+
+    let e = v2 - v1;
+    let n = Vec2::new(e.y, -e.x).normalize();
+    if edge.one_sided && dot(n, (b1 + b2) / 2.0 - v1) < 0.0 {
+        return None
+    }
+
+    let (normal, points) = match segment_segment((v1, v2, 0.0), (b1, b2, radius_b)) {
+        Some(contact) => contact,
+        None => return None,
+    };
+
+    // Contacts at the ends of a chain segment may belong to its neighbours
+    let (p, q) = closest_points_segments(v1, v2, b1, b2);
+    if (dist_sqr(p, v1) < EPSILON && in_previous_region(edge, q)) ||
+       (dist_sqr(p, v2) < EPSILON && in_next_region(edge, q)) {
+        return None
+    }
+    if edge.one_sided && dot(normal, n) < 0.0 {
+        return None
+    }
+
+    let points = points.into_iter().map(|(p, penetration)| (body_a.shape_to_world(p), penetration)).collect();
+    Some(ManifoldData::from_points((i_a, i_b), body_a.rotation() * normal, points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::types::PI;
+    use super::super::body::Shape;
+    use cgmath::Rad;
+
+    fn body<T: Into<Rad<Real>>>(shape: Shape, position: Vec2, angle: T) -> Body {
+        let mut body = Body::new(shape, position);
+        body.set_orient(angle);
+        body
     }
-}
\ No newline at end of file
+
+    fn collide_polygons(body_a: &Body, body_b: &Body) -> Option<ManifoldData> {
+        match (&body_a.shape, &body_b.shape) {
+            (&Shape::Polygon { orientation: ref o1, vertices: ref v1, radius: r1 },
+             &Shape::Polygon { orientation: ref o2, vertices: ref v2, radius: r2 }) => {
+                polygon_polygon((0, o1, v1, r1, body_a), (1, o2, v2, r2, body_b))
+            }
+            _ => panic!("not two polygons"),
+        }
+    }
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!((a - b).magnitude() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn box_resting_on_box() {
+        let a = body(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 0.0), Rad(0.0));
+        let b = body(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(2.0, 19.5), Rad(0.0));
+
+        let data = collide_polygons(&a, &b).unwrap();
+        assert_close(data.normal, Vec2::new(0.0, 1.0));
+        assert!(float_cmp(data.penetration, 0.5));
+        assert_eq!(data.contacts.len(), 2);
+        assert!(data.penetrations.iter().all(|&penetration| float_cmp(penetration, 0.5)));
+        for contact in &data.contacts {
+            assert!(float_cmp(contact.y, 9.75));
+            assert!(contact.x >= -8.0 - EPSILON && contact.x <= 10.0 + EPSILON);
+        }
+
+        // The normal always points from a to b
+        let data = collide_polygons(&b, &a).unwrap();
+        assert_close(data.normal, Vec2::new(0.0, -1.0));
+        assert_eq!(data.contacts.len(), 2);
+    }
+
+    #[test]
+    fn rotated_box_corner() {
+        let corner = 10.0 * (2.0 as Real).sqrt();
+        let a = body(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 0.0), Rad(0.0));
+        let b = body(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 10.0 + corner - 1.0), Rad(PI / 4.0));
+
+        let data = collide_polygons(&a, &b).unwrap();
+        assert_close(data.normal, Vec2::new(0.0, 1.0));
+        assert!((data.penetration - 1.0).abs() < 1e-3);
+        assert_eq!(data.contacts.len(), 1);
+        assert_close(data.contacts[0], Vec2::new(0.0, 9.5));
+
+        // Same contact when the reference face belongs to b
+        let data = collide_polygons(&b, &a).unwrap();
+        assert_close(data.normal, Vec2::new(0.0, -1.0));
+        assert!((data.penetration - 1.0).abs() < 1e-3);
+        assert_eq!(data.contacts.len(), 1);
+    }
+
+    #[test]
+    fn separated_boxes() {
+        let a = body(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 0.0), Rad(0.0));
+        let below = body(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 20.5), Rad(0.0));
+        let beside = body(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(25.0, 3.0), Rad(0.3));
+
+        assert!(collide_polygons(&a, &below).is_none());
+        assert!(collide_polygons(&a, &beside).is_none());
+        assert!(collide_polygons(&beside, &a).is_none());
+    }
+
+    #[test]
+    fn rounded_boxes_touch_at_sum_of_radii() {
+        // With the centers 25 apart the cores are 5 apart, exactly the two skins
+        let a = body(Shape::rounded_rect(Vec2::new(10.0, 10.0), 2.0), Vec2::new(0.0, 0.0), Rad(0.0));
+        let touching = body(Shape::rounded_rect(Vec2::new(10.0, 10.0), 3.0), Vec2::new(0.0, 25.0), Rad(0.0));
+        let overlapping = body(Shape::rounded_rect(Vec2::new(10.0, 10.0), 3.0), Vec2::new(0.0, 24.5), Rad(0.0));
+        let apart = body(Shape::rounded_rect(Vec2::new(10.0, 10.0), 3.0), Vec2::new(0.0, 25.5), Rad(0.0));
+
+        let data = collide_polygons(&a, &touching).unwrap();
+        assert!(float_cmp(data.penetration, 0.0));
+
+        let data = collide_polygons(&a, &overlapping).unwrap();
+        assert_close(data.normal, Vec2::new(0.0, 1.0));
+        assert!(float_cmp(data.penetration, 0.5));
+        assert_eq!(data.contacts.len(), 2);
+        // Halfway between the surface of a at 12 and the surface of b at 11.5
+        assert!(data.contacts.iter().all(|contact| float_cmp(contact.y, 11.75)));
+
+        assert!(collide_polygons(&a, &apart).is_none());
+    }
+
+    #[test]
+    fn circle_inside_rounded_core() {
+        let circle = body(Shape::Circle { radius: 3.0 }, Vec2::new(1.0, 2.0), Rad(0.0));
+        let b = body(Shape::rounded_rect(Vec2::new(10.0, 10.0), 2.0), Vec2::new(0.0, 0.0), Rad(0.3));
+
+        let data = match b.shape {
+            Shape::Polygon { ref orientation, ref vertices, radius } => {
+                circle_polygon((0, 3.0, &circle), (1, orientation, vertices, radius, &b)).unwrap()
+            }
+            _ => unreachable!(),
+        };
+        assert!(float_cmp(data.penetration, 3.0 + 2.0));
+        assert_eq!(data.penetrations, vec![data.penetration]);
+    }
+}
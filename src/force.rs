@@ -0,0 +1,196 @@
+use super::types::{Vec2, Real};
+use super::body::Body;
+use super::scene::{BodyIndex, GRAVITY};
+use super::operations::cross_real_vector;
+use cgmath::InnerSpace;
+
+// Adds forces to bodies before they are integrated. The forces only last for
+// the step they were applied in.
+pub trait ForceGenerator {
+    fn apply(&mut self, bodies: &mut [Body], delta: Real);
+}
+
+#[derive(Clone, Debug)]
+pub enum Region {
+    Everywhere,
+    Aabb { min: Vec2, max: Vec2 },
+    Circle { center: Vec2, radius: Real },
+}
+
+impl Region {
+    pub fn contains(&self, point: Vec2) -> bool {
+        match self {
+            &Region::Everywhere => true,
+            &Region::Aabb { min, max } => {
+                point.x >= min.x && point.x <= max.x && point.y >= min.y && point.y <= max.y
+            }
+            &Region::Circle { center, radius } => (point - center).magnitude2() <= radius * radius,
+        }
+    }
+}
+
+// Restricts a generator to the dynamic bodies in a region, optionally only the listed ones
+#[derive(Clone, Debug)]
+pub struct ForceFilter {
+    pub region: Region,
+    pub bodies: Option<Vec<BodyIndex>>,
+}
+
+impl ForceFilter {
+    pub fn everywhere() -> Self {
+        ForceFilter {
+            region: Region::Everywhere,
+            bodies: None,
+        }
+    }
+
+    pub fn accepts(&self, index: BodyIndex, body: &Body) -> bool {
+        body.is_dynamic() &&
+        self.region.contains(body.position) &&
+        self.bodies.as_ref().map_or(true, |bodies| bodies.contains(&index))
+    }
+}
+
+// Drags bodies towards the wind velocity
+#[derive(Clone, Debug)]
+pub struct Wind {
+    pub velocity: Vec2,
+    pub drag: Real,
+    pub filter: ForceFilter,
+}
+
+impl ForceGenerator for Wind {
+    fn apply(&mut self, bodies: &mut [Body], _: Real) {
+        for (index, body) in bodies.iter_mut().enumerate() {
+            if self.filter.accepts(index, body) {
+                let force = (self.velocity - body.velocity) * self.drag;
+                body.apply_force(force);
+            }
+        }
+    }
+}
+
+// Pulls bodies towards center with strength * mass / distance², a negative strength pushes
+// them away. Distances are clamped to min_distance to keep the force finite.
+#[derive(Clone, Debug)]
+pub struct PointGravity {
+    pub center: Vec2,
+    pub strength: Real,
+    pub min_distance: Real,
+    pub filter: ForceFilter,
+}
+
+impl ForceGenerator for PointGravity {
+    fn apply(&mut self, bodies: &mut [Body], _: Real) {
+        for (index, body) in bodies.iter_mut().enumerate() {
+            if !self.filter.accepts(index, body) {
+                continue
+            }
+
+            let d = self.center - body.position;
+            let distance = d.magnitude().max(self.min_distance);
+            if distance == 0.0 {
+                continue
+            }
+
+            let force = d / distance * (self.strength * body.mass / (distance * distance));
+            body.apply_force(force);
+        }
+    }
+}
+
+// Swirls bodies around center. The tangential force falls off linearly to zero
+// at radius, pull adds an inward component.
+#[derive(Clone, Debug)]
+pub struct Vortex {
+    pub center: Vec2,
+    pub radius: Real,
+    pub strength: Real,
+    pub pull: Real,
+    pub filter: ForceFilter,
+}
+
+impl ForceGenerator for Vortex {
+    fn apply(&mut self, bodies: &mut [Body], _: Real) {
+        for (index, body) in bodies.iter_mut().enumerate() {
+            if !self.filter.accepts(index, body) {
+                continue
+            }
+
+            let d = body.position - self.center;
+            let distance = d.magnitude();
+            if distance == 0.0 || distance >= self.radius {
+                continue
+            }
+
+            let falloff = 1.0 - distance / self.radius;
+            let direction = d / distance;
+            let tangent = cross_real_vector(1.0, direction);
+
+            let force = (tangent * self.strength - direction * self.pull) * (falloff * body.mass);
+            body.apply_force(force);
+        }
+    }
+}
+
+// A volume of fluid that pushes bodies up by the weight of the fluid they
+// displace and drags them along with its flow, like b2BuoyancyController
+#[derive(Clone, Debug)]
+pub struct Fluid {
+    pub vertices: Vec<Vec2>,
+    pub density: Real,
+    pub linear_drag: Real,
+    pub angular_drag: Real,
+    pub velocity: Vec2,
+    pub filter: ForceFilter,
+}
+
+impl Fluid {
+    // The vertices are a convex polygon in counter clockwise order, like Shape::Polygon
+    pub fn polygon(vertices: Vec<Vec2>, density: Real) -> Self {
+        Fluid {
+            vertices: vertices,
+            density: density,
+            linear_drag: 2.0,
+            angular_drag: 1.0,
+            velocity: Vec2::new(0.0, 0.0),
+            filter: ForceFilter::everywhere(),
+        }
+    }
+
+    pub fn aabb(min: Vec2, max: Vec2, density: Real) -> Self {
+        Self::polygon(vec![
+            Vec2::new(min.x, min.y),
+            Vec2::new(max.x, min.y),
+            Vec2::new(max.x, max.y),
+            Vec2::new(min.x, max.y),
+        ], density)
+    }
+}
+
+impl ForceGenerator for Fluid {
+    // b2BuoyancyController::Step
+    fn apply(&mut self, bodies: &mut [Body], _: Real) {
+        let gravity = Vec2::new(GRAVITY[0], GRAVITY[1]);
+
+        for (index, body) in bodies.iter_mut().enumerate() {
+            if !self.filter.accepts(index, body) {
+                continue
+            }
+
+            let (area, centroid) = body.submerged_area(&self.vertices);
+            if area <= 0.0 {
+                continue
+            }
+
+            let buoyancy = -gravity * self.density * area;
+            body.apply_force_at_point(buoyancy, centroid);
+
+            let drag = (self.velocity - body.velocity_at_point(centroid)) * (self.linear_drag * area);
+            body.apply_force_at_point(drag, centroid);
+
+            let angular_drag = body.moment_inertia * body.inv_mass * area * self.angular_drag;
+            body.apply_torque(-angular_drag * body.angular_velocity);
+        }
+    }
+}
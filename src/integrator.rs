@@ -0,0 +1,263 @@
+use super::types::{Vec2, Real};
+use super::body::{Body, BodyType};
+use super::scene::GRAVITY;
+use cgmath::Rad;
+
+// How bodies are moved by their forces and velocities each step. The solver
+// runs between integrate_forces and integrate_velocities.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Integrator {
+    // Half the forces before the solver and half after, like ImpulseEngine
+    ImpulseEngine,
+    // Forces before the solver, then positions from the new velocities
+    SymplecticEuler,
+    // Half kick, drift, then half kick with the forces recomputed at the new position
+    VelocityVerlet,
+    // Runge-Kutta 4 for bodies the solver doesn't touch this step, with the forces
+    // recomputed at every stage. Constrained bodies fall back to symplectic Euler.
+    Rk4,
+}
+
+// Linear and angular acceleration from the accumulated forces and gravity
+fn acceleration(body: &Body) -> (Vec2, Real) {
+    let gravity = Vec2::new(GRAVITY[0], GRAVITY[1]);
+    (body.force * body.inv_mass + gravity, body.torque * body.inv_inertia)
+}
+
+fn kick(body: &mut Body, linear: Vec2, angular: Real, delta: Real) {
+    body.velocity += body.constrain_translation(linear * delta);
+    body.angular_velocity += angular * delta;
+}
+
+fn drift(body: &mut Body, delta: Real) {
+    body.position += body.constrain_translation(body.velocity * delta);
+    let orient = body.orient.0 + body.angular_velocity * delta;
+    body.set_orient(Rad(orient));
+}
+
+// Position, orientation and velocities of a body, or their derivatives
+#[derive(Clone, Copy)]
+struct State {
+    position: Vec2,
+    orient: Real,
+    velocity: Vec2,
+    angular_velocity: Real,
+}
+
+impl State {
+    fn of(body: &Body) -> Self {
+        State {
+            position: body.position,
+            orient: body.orient.0,
+            velocity: body.velocity,
+            angular_velocity: body.angular_velocity,
+        }
+    }
+
+    fn derivative(body: &Body) -> Self {
+        let (linear, angular) = acceleration(body);
+        State {
+            position: body.velocity,
+            orient: body.angular_velocity,
+            velocity: body.constrain_translation(linear),
+            angular_velocity: angular,
+        }
+    }
+
+    // self + derivative * delta, moving only along the unlocked axes of body
+    fn step(&self, derivative: &State, delta: Real, body: &Body) -> Self {
+        State {
+            position: self.position + body.constrain_translation(derivative.position * delta),
+            orient: self.orient + derivative.orient * delta,
+            velocity: self.velocity + body.constrain_translation(derivative.velocity * delta),
+            angular_velocity: self.angular_velocity + derivative.angular_velocity * delta,
+        }
+    }
+
+    fn apply(&self, body: &mut Body) {
+        body.position = self.position;
+        body.velocity = self.velocity;
+        body.angular_velocity = self.angular_velocity;
+        body.set_orient(Rad(self.orient));
+    }
+}
+
+// Moves the bodies marked in free over delta with RK4. The forces are recomputed
+// by update_forces with all bodies at the state of each stage.
+fn rk4<F: FnMut(&mut [Body])>(bodies: &mut [Body], free: &[bool], delta: Real, update_forces: &mut F) {
+    let start = bodies.iter().map(State::of).collect::<Vec<_>>();
+    let mut derivatives: Vec<[State; 4]> = Vec::with_capacity(bodies.len());
+    for body in bodies.iter() {
+        let d = State::derivative(body);
+        derivatives.push([d, d, d, d]);
+    }
+
+    for stage in 1..4 {
+        let fraction = if stage == 3 { 1.0 } else { 0.5 };
+        for index in 0..bodies.len() {
+            if free[index] {
+                let state = start[index].step(&derivatives[index][stage - 1], delta * fraction, &bodies[index]);
+                state.apply(&mut bodies[index]);
+            }
+        }
+
+        update_forces(bodies);
+        for index in 0..bodies.len() {
+            derivatives[index][stage] = State::derivative(&bodies[index]);
+        }
+    }
+
+    for index in 0..bodies.len() {
+        if free[index] {
+            let d = &derivatives[index];
+            let sum = State {
+                position: d[0].position + d[1].position * 2.0 + d[2].position * 2.0 + d[3].position,
+                orient: d[0].orient + d[1].orient * 2.0 + d[2].orient * 2.0 + d[3].orient,
+                velocity: d[0].velocity + d[1].velocity * 2.0 + d[2].velocity * 2.0 + d[3].velocity,
+                angular_velocity: d[0].angular_velocity + d[1].angular_velocity * 2.0 +
+                                  d[2].angular_velocity * 2.0 + d[3].angular_velocity,
+            };
+            let state = start[index].step(&sum, delta / 6.0, &bodies[index]);
+            state.apply(&mut bodies[index]);
+        }
+    }
+}
+
+impl Integrator {
+    pub fn integrate_forces(&self, body: &mut Body, delta: Real, constrained: bool) {
+        if body.body_type != BodyType::Dynamic {
+            return
+        }
+
+        let (linear, angular) = acceleration(body);
+        match *self {
+            Integrator::ImpulseEngine => body.integrate_forces(delta),
+            Integrator::SymplecticEuler => kick(body, linear, angular, delta),
+            Integrator::VelocityVerlet => kick(body, linear, angular, delta / 2.0),
+            Integrator::Rk4 => if constrained {
+                kick(body, linear, angular, delta)
+            },
+        }
+    }
+
+    // Moves the bodies after the solver. update_forces recomputes the forces on
+    // every body from its current state, for the integrators that need the forces
+    // somewhere else than at the start of the step.
+    pub fn integrate_velocities<F: FnMut(&mut [Body])>(&self, bodies: &mut [Body], constrained: &[bool], delta: Real, mut update_forces: F) {
+        if *self == Integrator::Rk4 {
+            let free = bodies.iter().zip(constrained)
+                .map(|(body, &constrained)| body.body_type == BodyType::Dynamic && !constrained)
+                .collect::<Vec<_>>();
+            rk4(bodies, &free, delta, &mut update_forces);
+
+            for (body, &free) in bodies.iter_mut().zip(&free) {
+                if !free && body.body_type != BodyType::Static {
+                    drift(body, delta);
+                }
+            }
+            return
+        }
+
+        for body in bodies.iter_mut() {
+            match body.body_type {
+                BodyType::Static => (),
+                BodyType::Kinematic => drift(body, delta),
+                BodyType::Dynamic => match *self {
+                    Integrator::ImpulseEngine => body.integrate_velocity(delta),
+                    _ => drift(body, delta),
+                },
+            }
+        }
+
+        if *self == Integrator::VelocityVerlet {
+            update_forces(bodies);
+            for body in bodies.iter_mut().filter(|body| body.body_type == BodyType::Dynamic) {
+                let (linear, angular) = acceleration(body);
+                kick(body, linear, angular, delta / 2.0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::body::Shape;
+    use super::super::force::ForceGenerator;
+    use super::super::scene::{Scene, FRAME_TIME};
+    use super::super::types::PI;
+    use cgmath::{dot, InnerSpace};
+
+    // Pulls every body towards the origin with stiffness * mass * distance
+    struct Spring {
+        stiffness: Real,
+    }
+
+    impl ForceGenerator for Spring {
+        fn apply(&mut self, bodies: &mut [Body], _: Real) {
+            for body in bodies.iter_mut().filter(|body| body.is_dynamic()) {
+                let force = -body.position * (self.stiffness * body.mass);
+                body.apply_force(force);
+            }
+        }
+    }
+
+    fn energy(body: &Body, stiffness: Real) -> Real {
+        let gravity = Vec2::new(GRAVITY[0], GRAVITY[1]);
+        body.mass * (0.5 * body.velocity.magnitude2() + 0.5 * stiffness * body.position.magnitude2() -
+                     dot(gravity, body.position))
+    }
+
+    // Largest energy error over a few periods of a spring, relative to the energy swing
+    fn energy_drift(integrator: Integrator) -> Real {
+        let omega = 2.0 * PI;
+        let stiffness = omega * omega;
+
+        let mut scene = Scene::new();
+        scene.integrator = integrator;
+        scene.add_force_generator(Spring { stiffness: stiffness });
+        let index = scene.add(Body::new(Shape::Circle { radius: 5.0 }, Vec2::new(100.0, 0.0)));
+
+        let start = energy(&scene.bodies[index], stiffness);
+        let scale = scene.bodies[index].mass * 0.5 * stiffness * 100.0 * 100.0;
+        let mut drift: Real = 0.0;
+        for _ in 0..300 {
+            scene.step(FRAME_TIME);
+            drift = drift.max((energy(&scene.bodies[index], stiffness) - start).abs() / scale);
+        }
+        drift
+    }
+
+    #[test]
+    fn energy_drift_of_the_integrators() {
+        let euler = energy_drift(Integrator::SymplecticEuler);
+        let verlet = energy_drift(Integrator::VelocityVerlet);
+        let rk4 = energy_drift(Integrator::Rk4);
+
+        assert!(verlet < euler, "verlet {} euler {}", verlet, euler);
+        assert!(rk4 < euler, "rk4 {} euler {}", rk4, euler);
+        assert!(verlet < 0.05 && rk4 < 0.05, "verlet {} rk4 {}", verlet, rk4);
+    }
+
+    #[test]
+    fn constrained_bodies_fall_back_to_symplectic_euler() {
+        let run = |integrator| {
+            let mut scene = Scene::new();
+            scene.integrator = integrator;
+            let mut ground = Body::new(Shape::rect(Vec2::new(100.0, 10.0)), Vec2::new(0.0, 20.0));
+            ground.set_static();
+            scene.add(ground);
+            let index = scene.add(Body::new(Shape::Circle { radius: 10.0 }, Vec2::new(0.0, 0.5)));
+            scene.bodies[index].velocity = Vec2::new(30.0, 0.0);
+
+            for _ in 0..10 {
+                scene.step(FRAME_TIME);
+            }
+            let body = &scene.bodies[index];
+            (body.position, body.velocity, body.orient, body.angular_velocity)
+        };
+
+        assert_eq!(run(Integrator::Rk4), run(Integrator::SymplecticEuler));
+    }
+}
@@ -0,0 +1,246 @@
+use super::super::types::{Vec2, Real};
+use super::super::body::Body;
+use super::super::scene::BodyIndex;
+use super::super::operations::cross_vectors;
+use super::{Joint, move_body};
+use cgmath::{Matrix, Zero, dot};
+
+// One of the two joints coupled by a gear. A revolute joint has no axis.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+struct GearSide {
+    local_anchor_ground: Vec2,
+    local_anchor_body: Vec2,
+    local_axis: Option<Vec2>,
+    reference_angle: Real,
+}
+
+impl GearSide {
+    // Returns the side, its ground body and its moving body
+    fn from_joint(joint: &Joint) -> Option<(GearSide, BodyIndex, BodyIndex)> {
+        match joint {
+            &Joint::Revolute(ref revolute) => Some((GearSide {
+                local_anchor_ground: revolute.local_anchor_a,
+                local_anchor_body: revolute.local_anchor_b,
+                local_axis: None,
+                reference_angle: revolute.reference_angle,
+            }, revolute.bodies.0, revolute.bodies.1)),
+            &Joint::Prismatic(ref prismatic) => Some((GearSide {
+                local_anchor_ground: prismatic.local_anchor_a,
+                local_anchor_body: prismatic.local_anchor_b,
+                local_axis: Some(prismatic.local_axis_a),
+                reference_angle: prismatic.reference_angle,
+            }, prismatic.bodies.0, prismatic.bodies.1)),
+            _ => None
+        }
+    }
+
+    fn shift_anchors(&mut self, (ground, moving): (BodyIndex, BodyIndex), body: BodyIndex, shift: Vec2) {
+        if ground == body {
+            self.local_anchor_ground += shift;
+        }
+        if moving == body {
+            self.local_anchor_body += shift;
+        }
+    }
+
+    // Joint angle for revolute joints, joint translation for prismatic ones
+    fn coordinate(&self, ground: &Body, body: &Body) -> Real {
+        match self.local_axis {
+            None => body.orient.0 - ground.orient.0 - self.reference_angle,
+            Some(axis) => {
+                let p_ground = self.local_anchor_ground;
+                let p_body = ground.rotation().transpose() *
+                    (body.rotation() * self.local_anchor_body + (body.position - ground.position));
+                dot(p_body - p_ground, axis)
+            }
+        }
+    }
+
+    // Returns (linear jacobian, angular jacobian of the body, angular jacobian of
+    // the ground, inverse effective mass)
+    fn jacobian(&self, ground: &Body, body: &Body) -> (Vec2, Real, Real, Real) {
+        match self.local_axis {
+            None => (Vec2::zero(), 1.0, 1.0, body.inv_inertia + ground.inv_inertia),
+            Some(axis) => {
+                let u = ground.rotation() * axis;
+                let r_ground = ground.rotation() * self.local_anchor_ground;
+                let r_body = body.rotation() * self.local_anchor_body;
+
+                let jw_ground = cross_vectors(r_ground, u);
+                let jw_body = cross_vectors(r_body, u);
+                let inv_mass = ground.inv_mass_along(u) + body.inv_mass_along(u) +
+                               ground.inv_inertia * jw_ground * jw_ground +
+                               body.inv_inertia * jw_body * jw_body;
+
+                (u, jw_body, jw_ground, inv_mass)
+            }
+        }
+    }
+}
+
+// Couples two revolute or prismatic joints so that
+// coordinate_a + ratio * coordinate_b stays constant.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct GearJoint {
+    pub bodies: (BodyIndex, BodyIndex),
+    pub grounds: (BodyIndex, BodyIndex),
+    pub ratio: Real,
+    pub collide_connected: bool,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    side_a: GearSide,
+    side_b: GearSide,
+    constant: Real,
+    impulse: Real,
+    mass: Real,
+    jv_ac: Vec2,
+    jv_bd: Vec2,
+    jw_a: Real,
+    jw_b: Real,
+    jw_c: Real,
+    jw_d: Real,
+}
+
+impl GearJoint {
+    // b2GearJointDef, returns None unless both joints are revolute or prismatic
+    pub fn new(joint_a: &Joint, joint_b: &Joint, bodies: &[Body], ratio: Real) -> Option<Self> {
+        let (side_a, ground_a, body_a) = match GearSide::from_joint(joint_a) {
+            Some(side) => side,
+            None => return None,
+        };
+        let (side_b, ground_b, body_b) = match GearSide::from_joint(joint_b) {
+            Some(side) => side,
+            None => return None,
+        };
+
+        let coordinate_a = side_a.coordinate(&bodies[ground_a], &bodies[body_a]);
+        let coordinate_b = side_b.coordinate(&bodies[ground_b], &bodies[body_b]);
+
+        Some(GearJoint {
+            bodies: (body_a, body_b),
+            grounds: (ground_a, ground_b),
+            ratio: ratio,
+            collide_connected: false,
+            break_force: None,
+            break_torque: None,
+
+            side_a: side_a,
+            side_b: side_b,
+            constant: coordinate_a + ratio * coordinate_b,
+            impulse: 0.0,
+            mass: 0.0,
+            jv_ac: Vec2::zero(),
+            jv_bd: Vec2::zero(),
+            jw_a: 0.0,
+            jw_b: 0.0,
+            jw_c: 0.0,
+            jw_d: 0.0,
+        })
+    }
+
+    // Joint::shift_anchors for the copies of the anchors of the coupled joints
+    pub fn shift_anchors(&mut self, body: BodyIndex, shift: Vec2) {
+        self.side_a.shift_anchors((self.grounds.0, self.bodies.0), body, shift);
+        self.side_b.shift_anchors((self.grounds.1, self.bodies.1), body, shift);
+    }
+
+    // Computes the jacobians for the current positions and returns the inverse effective mass
+    fn update_jacobian(&mut self, bodies: &[Body]) -> Real {
+        let (index_a, index_b) = self.bodies;
+        let (index_c, index_d) = self.grounds;
+
+        let (jv_ac, jw_a, jw_c, inv_mass_a) = self.side_a.jacobian(&bodies[index_c], &bodies[index_a]);
+        let (jv_bd, jw_b, jw_d, inv_mass_b) = self.side_b.jacobian(&bodies[index_d], &bodies[index_b]);
+
+        self.jv_ac = jv_ac;
+        self.jw_a = jw_a;
+        self.jw_c = jw_c;
+        self.jv_bd = self.ratio * jv_bd;
+        self.jw_b = self.ratio * jw_b;
+        self.jw_d = self.ratio * jw_d;
+
+        inv_mass_a + self.ratio * self.ratio * inv_mass_b
+    }
+
+    // b2GearJoint::GetReactionForce
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        self.jv_ac * self.impulse * inv_delta
+    }
+
+    // b2GearJoint::GetReactionTorque
+    pub fn reaction_torque(&self, inv_delta: Real) -> Real {
+        self.jw_a * self.impulse * inv_delta
+    }
+
+    // b2GearJoint::InitVelocityConstraints
+    pub fn init_velocity(&mut self, bodies: &mut [Body], _: Real) {
+        let inv_mass = self.update_jacobian(bodies);
+        self.mass = if inv_mass > 0.0 { 1.0 / inv_mass } else { 0.0 };
+
+        let impulse = self.impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2GearJoint::SolveVelocityConstraints
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let c_dot = {
+            let (body_a, body_b) = (&bodies[self.bodies.0], &bodies[self.bodies.1]);
+            let (body_c, body_d) = (&bodies[self.grounds.0], &bodies[self.grounds.1]);
+
+            dot(self.jv_ac, body_a.velocity - body_c.velocity) +
+            dot(self.jv_bd, body_b.velocity - body_d.velocity) +
+            (self.jw_a * body_a.angular_velocity - self.jw_c * body_c.angular_velocity) +
+            (self.jw_b * body_b.angular_velocity - self.jw_d * body_d.angular_velocity)
+        };
+
+        let impulse = -self.mass * c_dot;
+        self.impulse += impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2GearJoint::SolvePositionConstraints
+    pub fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (index_a, index_b) = self.bodies;
+        let (index_c, index_d) = self.grounds;
+
+        let inv_mass = self.update_jacobian(bodies);
+
+        let coordinate_a = self.side_a.coordinate(&bodies[index_c], &bodies[index_a]);
+        let coordinate_b = self.side_b.coordinate(&bodies[index_d], &bodies[index_b]);
+        let c = coordinate_a + self.ratio * coordinate_b - self.constant;
+
+        let impulse = if inv_mass > 0.0 { -c / inv_mass } else { 0.0 };
+
+        let moves = [
+            (index_a, impulse * self.jv_ac, impulse * self.jw_a),
+            (index_b, impulse * self.jv_bd, impulse * self.jw_b),
+            (index_c, -impulse * self.jv_ac, -impulse * self.jw_c),
+            (index_d, -impulse * self.jv_bd, -impulse * self.jw_d),
+        ];
+        for &(index, translation, rotation) in &moves {
+            let body = &mut bodies[index];
+            let (m, i) = (body.inv_mass, body.inv_inertia);
+            move_body(body, m * translation, i * rotation);
+        }
+
+        // The gear is solved by its underlying joints, like b2GearJoint
+        true
+    }
+
+    fn apply(&self, bodies: &mut [Body], impulse: Real) {
+        let impulses = [
+            (self.bodies.0, impulse * self.jv_ac, impulse * self.jw_a),
+            (self.bodies.1, impulse * self.jv_bd, impulse * self.jw_b),
+            (self.grounds.0, -impulse * self.jv_ac, -impulse * self.jw_c),
+            (self.grounds.1, -impulse * self.jv_bd, -impulse * self.jw_d),
+        ];
+        for &(index, linear, angular) in &impulses {
+            let body = &mut bodies[index];
+            body.apply_impulse(linear, Vec2::zero());
+            body.apply_angular_impulse(angular);
+        }
+    }
+}
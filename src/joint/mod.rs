@@ -0,0 +1,615 @@
+use super::types::{Vec2, Vec3, Mat2, Mat3, Real};
+use super::body::Body;
+use super::scene::BodyIndex;
+use cgmath::{Rad, InnerSpace, SquareMatrix, Zero};
+
+mod weld;
+mod mouse;
+mod revolute;
+mod prismatic;
+mod rope;
+mod pulley;
+mod gear;
+pub use self::weld::WeldJoint;
+pub use self::mouse::MouseJoint;
+pub use self::revolute::RevoluteJoint;
+pub use self::prismatic::PrismaticJoint;
+pub use self::rope::RopeJoint;
+pub use self::pulley::PulleyJoint;
+pub use self::gear::GearJoint;
+
+// Refers to a joint in a JointSet. Stays valid while the joint is in the set, and
+// doesn't match whatever joint later reuses its slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct JointHandle {
+    index: usize,
+    generation: u32,
+}
+
+// Position errors below these are considered solved (b2_linearSlop, b2_angularSlop)
+pub static LINEAR_SLOP : f32 = 0.05;
+pub static ANGULAR_SLOP : f32 = 2.0 / 180.0 * ::std::f32::consts::PI;
+// Largest position correction applied in one iteration (b2_maxLinearCorrection)
+pub static MAX_LINEAR_CORRECTION : f32 = 2.0;
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum Joint {
+    Weld(WeldJoint),
+    Mouse(MouseJoint),
+    Revolute(RevoluteJoint),
+    Prismatic(PrismaticJoint),
+    Rope(RopeJoint),
+    Pulley(PulleyJoint),
+    Gear(GearJoint),
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+struct JointSlot {
+    generation: u32,
+    joint: Option<Joint>,
+}
+
+// Joints by handle. Removing a joint frees its slot for the next one added, the
+// other handles are left alone.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct JointSet {
+    slots: Vec<JointSlot>,
+    free: Vec<usize>,
+}
+
+impl JointSet {
+    pub fn new() -> Self {
+        JointSet {
+            slots: vec![],
+            free: vec![],
+        }
+    }
+
+    pub fn insert(&mut self, joint: Joint) -> JointHandle {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.joint = Some(joint);
+                JointHandle { index: index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(JointSlot { generation: 0, joint: Some(joint) });
+                JointHandle { index: self.slots.len() - 1, generation: 0 }
+            }
+        }
+    }
+
+    // None if the joint was already removed
+    pub fn remove(&mut self, handle: JointHandle) -> Option<Joint> {
+        if self.get(handle).is_none() {
+            return None
+        }
+
+        let slot = &mut self.slots[handle.index];
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        slot.joint.take()
+    }
+
+    pub fn get(&self, handle: JointHandle) -> Option<&Joint> {
+        match self.slots.get(handle.index) {
+            Some(slot) if slot.generation == handle.generation => slot.joint.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: JointHandle) -> Option<&mut Joint> {
+        match self.slots.get_mut(handle.index) {
+            Some(slot) if slot.generation == handle.generation => slot.joint.as_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn contains(&self, handle: JointHandle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Joints in slot order, which is the order they're solved in
+    pub fn iter(&self) -> impl Iterator<Item = (JointHandle, &Joint)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.joint.as_ref().map(|joint| (JointHandle { index: index, generation: slot.generation }, joint))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (JointHandle, &mut Joint)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            let generation = slot.generation;
+            slot.joint.as_mut().map(|joint| (JointHandle { index: index, generation: generation }, joint))
+        })
+    }
+
+    pub fn joints_mut(&mut self) -> impl Iterator<Item = &mut Joint> {
+        self.iter_mut().map(|(_, joint)| joint)
+    }
+}
+
+impl Joint {
+    // Whether this joint connects the two bodies, in any order
+    pub fn connects(&self, i_a: BodyIndex, i_b: BodyIndex) -> bool {
+        let (a, b) = match self {
+            &Joint::Weld(ref joint) => joint.bodies,
+            &Joint::Mouse(_) => return false,
+            &Joint::Revolute(ref joint) => joint.bodies,
+            &Joint::Prismatic(ref joint) => joint.bodies,
+            &Joint::Rope(ref joint) => joint.bodies,
+            &Joint::Pulley(ref joint) => joint.bodies,
+            &Joint::Gear(ref joint) => joint.bodies,
+        };
+        (a == i_a && b == i_b) || (a == i_b && b == i_a)
+    }
+
+    // Every body the joint reads or writes while solving
+    pub fn bodies(&self) -> Vec<BodyIndex> {
+        match self {
+            &Joint::Weld(ref joint) => vec![joint.bodies.0, joint.bodies.1],
+            &Joint::Mouse(ref joint) => vec![joint.body],
+            &Joint::Revolute(ref joint) => vec![joint.bodies.0, joint.bodies.1],
+            &Joint::Prismatic(ref joint) => vec![joint.bodies.0, joint.bodies.1],
+            &Joint::Rope(ref joint) => vec![joint.bodies.0, joint.bodies.1],
+            &Joint::Pulley(ref joint) => vec![joint.bodies.0, joint.bodies.1],
+            &Joint::Gear(ref joint) => vec![joint.bodies.0, joint.bodies.1, joint.grounds.0, joint.grounds.1],
+        }
+    }
+
+    pub fn collide_connected(&self) -> bool {
+        match self {
+            &Joint::Weld(ref joint) => joint.collide_connected,
+            &Joint::Mouse(_) => true,
+            &Joint::Revolute(ref joint) => joint.collide_connected,
+            &Joint::Prismatic(ref joint) => joint.collide_connected,
+            &Joint::Rope(ref joint) => joint.collide_connected,
+            &Joint::Pulley(ref joint) => joint.collide_connected,
+            &Joint::Gear(ref joint) => joint.collide_connected,
+        }
+    }
+
+    // Constraint force applied on the second body during the last step
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        match self {
+            &Joint::Weld(ref joint) => joint.reaction_force(inv_delta),
+            &Joint::Mouse(ref joint) => joint.reaction_force(inv_delta),
+            &Joint::Revolute(ref joint) => joint.reaction_force(inv_delta),
+            &Joint::Prismatic(ref joint) => joint.reaction_force(inv_delta),
+            &Joint::Rope(ref joint) => joint.reaction_force(inv_delta),
+            &Joint::Pulley(ref joint) => joint.reaction_force(inv_delta),
+            &Joint::Gear(ref joint) => joint.reaction_force(inv_delta),
+        }
+    }
+
+    // Constraint torque applied on the second body during the last step
+    pub fn reaction_torque(&self, inv_delta: Real) -> Real {
+        match self {
+            &Joint::Weld(ref joint) => joint.reaction_torque(inv_delta),
+            &Joint::Mouse(ref joint) => joint.reaction_torque(inv_delta),
+            &Joint::Revolute(ref joint) => joint.reaction_torque(inv_delta),
+            &Joint::Prismatic(ref joint) => joint.reaction_torque(inv_delta),
+            &Joint::Rope(ref joint) => joint.reaction_torque(inv_delta),
+            &Joint::Pulley(ref joint) => joint.reaction_torque(inv_delta),
+            &Joint::Gear(ref joint) => joint.reaction_torque(inv_delta),
+        }
+    }
+
+    // Whether the reaction of the last step exceeded the break force or torque
+    pub fn should_break(&self, inv_delta: Real) -> bool {
+        let (break_force, break_torque) = match self {
+            &Joint::Weld(ref joint) => (joint.break_force, joint.break_torque),
+            &Joint::Mouse(ref joint) => (joint.break_force, joint.break_torque),
+            &Joint::Revolute(ref joint) => (joint.break_force, joint.break_torque),
+            &Joint::Prismatic(ref joint) => (joint.break_force, joint.break_torque),
+            &Joint::Rope(ref joint) => (joint.break_force, joint.break_torque),
+            &Joint::Pulley(ref joint) => (joint.break_force, joint.break_torque),
+            &Joint::Gear(ref joint) => (joint.break_force, joint.break_torque),
+        };
+
+        break_force.map_or(false, |limit| self.reaction_force(inv_delta).magnitude() > limit) ||
+        break_torque.map_or(false, |limit| self.reaction_torque(inv_delta).abs() > limit)
+    }
+
+    // Moves the anchors on body by shift in body space, to keep them on the same
+    // point of the shape when the center of mass of the body moved by -shift
+    pub fn shift_anchors(&mut self, body: BodyIndex, shift: Vec2) {
+        fn shift_pair(bodies: (BodyIndex, BodyIndex), anchor_a: &mut Vec2, anchor_b: &mut Vec2, body: BodyIndex, shift: Vec2) {
+            if bodies.0 == body {
+                *anchor_a += shift;
+            }
+            if bodies.1 == body {
+                *anchor_b += shift;
+            }
+        }
+
+        match self {
+            &mut Joint::Weld(ref mut joint) => {
+                shift_pair(joint.bodies, &mut joint.local_anchor_a, &mut joint.local_anchor_b, body, shift)
+            }
+            &mut Joint::Mouse(ref mut joint) => {
+                if joint.body == body {
+                    joint.local_anchor += shift;
+                }
+            }
+            &mut Joint::Revolute(ref mut joint) => {
+                shift_pair(joint.bodies, &mut joint.local_anchor_a, &mut joint.local_anchor_b, body, shift)
+            }
+            &mut Joint::Prismatic(ref mut joint) => {
+                shift_pair(joint.bodies, &mut joint.local_anchor_a, &mut joint.local_anchor_b, body, shift)
+            }
+            &mut Joint::Rope(ref mut joint) => {
+                shift_pair(joint.bodies, &mut joint.local_anchor_a, &mut joint.local_anchor_b, body, shift)
+            }
+            &mut Joint::Pulley(ref mut joint) => {
+                shift_pair(joint.bodies, &mut joint.local_anchor_a, &mut joint.local_anchor_b, body, shift)
+            }
+            &mut Joint::Gear(ref mut joint) => joint.shift_anchors(body, shift),
+        }
+    }
+
+    pub fn init_velocity(&mut self, bodies: &mut [Body], delta: Real) {
+        match self {
+            &mut Joint::Weld(ref mut joint) => joint.init_velocity(bodies, delta),
+            &mut Joint::Mouse(ref mut joint) => joint.init_velocity(bodies, delta),
+            &mut Joint::Revolute(ref mut joint) => joint.init_velocity(bodies, delta),
+            &mut Joint::Prismatic(ref mut joint) => joint.init_velocity(bodies, delta),
+            &mut Joint::Rope(ref mut joint) => joint.init_velocity(bodies, delta),
+            &mut Joint::Pulley(ref mut joint) => joint.init_velocity(bodies, delta),
+            &mut Joint::Gear(ref mut joint) => joint.init_velocity(bodies, delta),
+        }
+    }
+
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        match self {
+            &mut Joint::Weld(ref mut joint) => joint.solve_velocity(bodies),
+            &mut Joint::Mouse(ref mut joint) => joint.solve_velocity(bodies),
+            &mut Joint::Revolute(ref mut joint) => joint.solve_velocity(bodies),
+            &mut Joint::Prismatic(ref mut joint) => joint.solve_velocity(bodies),
+            &mut Joint::Rope(ref mut joint) => joint.solve_velocity(bodies),
+            &mut Joint::Pulley(ref mut joint) => joint.solve_velocity(bodies),
+            &mut Joint::Gear(ref mut joint) => joint.solve_velocity(bodies),
+        }
+    }
+
+    // Returns true when the joint error is within the slop
+    pub fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        match self {
+            &mut Joint::Weld(ref mut joint) => joint.solve_position(bodies),
+            &mut Joint::Mouse(ref mut joint) => joint.solve_position(bodies),
+            &mut Joint::Revolute(ref mut joint) => joint.solve_position(bodies),
+            &mut Joint::Prismatic(ref mut joint) => joint.solve_position(bodies),
+            &mut Joint::Rope(ref mut joint) => joint.solve_position(bodies),
+            &mut Joint::Pulley(ref mut joint) => joint.solve_position(bodies),
+            &mut Joint::Gear(ref mut joint) => joint.solve_position(bodies),
+        }
+    }
+}
+
+fn move_body(body: &mut Body, translation: Vec2, rotation: Real) {
+    body.position += body.constrain_translation(translation);
+    let orient = body.orient.0 + rotation;
+    body.set_orient(Rad(orient));
+}
+
+// Inverse mass of a body seen by an impulse at r from its center of mass, the
+// body's part of K for a point constraint. Locked axes add no linear term.
+fn point_inv_mass(body: &Body, r: Vec2) -> Mat2 {
+    let (m, i) = (body.inv_mass_axes(), body.inv_inertia);
+    Mat2::new(
+        m.x + i * r.y * r.y, -i * r.x * r.y,
+        -i * r.x * r.y,      m.y + i * r.x * r.x,
+    )
+}
+
+// Inverse of K over the rows that can move, with softness added to them. Nothing
+// moves along a row that's zero, like a locked axis on a body that can't turn, so
+// it gets no impulse instead of making K singular.
+fn invert_free22(k: &Mat2, softness: Real) -> Mat2 {
+    let dead = [k.x.x <= 0.0, k.y.y <= 0.0];
+    let mut k = *k;
+    for i in 0..2 {
+        if dead[i] {
+            for j in 0..2 {
+                k[i][j] = 0.0;
+                k[j][i] = 0.0;
+            }
+            k[i][i] = 1.0;
+        } else {
+            k[i][i] += softness;
+        }
+    }
+
+    let mut inv = k.invert().unwrap_or(Mat2::zero());
+    for i in 0..2 {
+        if dead[i] {
+            inv[i][i] = 0.0;
+        }
+    }
+    inv
+}
+
+fn invert_free33(k: &Mat3) -> Mat3 {
+    let dead = [k.x.x <= 0.0, k.y.y <= 0.0, k.z.z <= 0.0];
+    let mut k = *k;
+    for i in 0..3 {
+        if dead[i] {
+            for j in 0..3 {
+                k[i][j] = 0.0;
+                k[j][i] = 0.0;
+            }
+            k[i][i] = 1.0;
+        }
+    }
+
+    let mut inv = k.invert().unwrap_or(Mat3::zero());
+    for i in 0..3 {
+        if dead[i] {
+            inv[i][i] = 0.0;
+        }
+    }
+    inv
+}
+
+fn upper_left(k: &Mat3) -> Mat2 {
+    Mat2::new(k.x.x, k.x.y, k.y.x, k.y.y)
+}
+
+// b2Mat33::GetInverse22
+fn inverse22(k: &Mat3) -> Mat3 {
+    let inv = invert_free22(&upper_left(k), 0.0);
+
+    Mat3::new(
+        inv.x.x, inv.x.y, 0.0,
+        inv.y.x, inv.y.y, 0.0,
+        0.0,     0.0,     0.0,
+    )
+}
+
+// b2Mat33::GetSymInverse33
+fn inverse33(k: &Mat3) -> Mat3 {
+    invert_free33(k)
+}
+
+// b2Mat33::Solve22
+fn solve22(k: &Mat3, b: Vec2) -> Vec2 {
+    invert_free22(&upper_left(k), 0.0) * b
+}
+
+// b2Mat33::Solve33
+fn solve33(k: &Mat3, b: Vec3) -> Vec3 {
+    invert_free33(k) * b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::body::Shape;
+    use super::super::scene::{Scene, Event, FRAME_TIME, GRAVITY};
+
+    fn ground(scene: &mut Scene, position: Vec2) -> BodyIndex {
+        let mut body = Body::new(Shape::Circle { radius: 5.0 }, position);
+        body.set_static();
+        scene.add(body)
+    }
+
+    // Where b sits in a's body space and their relative angle
+    fn relative_pose(scene: &Scene, a: BodyIndex, b: BodyIndex) -> (Vec2, Real) {
+        let (body_a, body_b) = (&scene.bodies[a], &scene.bodies[b]);
+        (body_a.local_point(body_b.position), body_b.orient.0 - body_a.orient.0)
+    }
+
+    #[test]
+    fn weld_holds_relative_pose() {
+        let mut scene = Scene::new();
+        let pivot = ground(&mut scene, Vec2::new(0.0, 0.0));
+        let arm = scene.add(Body::new(Shape::rect(Vec2::new(40.0, 5.0)), Vec2::new(40.0, 0.0)));
+        let weight = scene.add(Body::new(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(90.0, -20.0)));
+
+        let hinge = RevoluteJoint::new((pivot, &scene.bodies[pivot]), (arm, &scene.bodies[arm]), Vec2::new(0.0, 0.0));
+        scene.add_joint(Joint::Revolute(hinge));
+        let weld = WeldJoint::new((arm, &scene.bodies[arm]), (weight, &scene.bodies[weight]), Vec2::new(80.0, -10.0));
+        scene.add_joint(Joint::Weld(weld));
+
+        let (offset, angle) = relative_pose(&scene, arm, weight);
+        for _ in 0..120 {
+            scene.step(FRAME_TIME);
+            let (current_offset, current_angle) = relative_pose(&scene, arm, weight);
+            assert!((current_offset - offset).magnitude() < 1.0, "{:?} != {:?}", current_offset, offset);
+            assert!((current_angle - angle).abs() < 0.02, "{} != {}", current_angle, angle);
+        }
+
+        // The arm actually swung down
+        assert!(scene.bodies[arm].orient.0.abs() > 0.5);
+    }
+
+    #[test]
+    fn mouse_pulls_towards_target() {
+        let mut scene = Scene::new();
+        let body = scene.add(Body::new(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 0.0)));
+        let max_force = 1000.0 * scene.bodies[body].mass;
+        let mut mouse = MouseJoint::new((body, &scene.bodies[body]), Vec2::new(5.0, 5.0), max_force);
+        mouse.target = Vec2::new(100.0, -50.0);
+        let handle = scene.add_joint(Joint::Mouse(mouse));
+
+        for _ in 0..180 {
+            scene.step(FRAME_TIME);
+            let (force, _) = scene.joint_reaction(handle).unwrap();
+            assert!(force.magnitude() <= max_force * 1.001);
+        }
+
+        // The grabbed point hangs a little below the target under gravity
+        let anchor = scene.bodies[body].world_point(Vec2::new(5.0, 5.0));
+        assert!((anchor - Vec2::new(100.0, -50.0)).magnitude() < 5.0, "{:?}", anchor);
+    }
+
+    #[test]
+    fn rope_never_exceeds_max_length() {
+        let mut scene = Scene::new();
+        let anchor = ground(&mut scene, Vec2::new(0.0, 0.0));
+        let ball = scene.add(Body::new(Shape::Circle { radius: 5.0 }, Vec2::new(100.0, 0.0)));
+        scene.bodies[ball].velocity = Vec2::new(200.0, -400.0);
+
+        let rope = RopeJoint::new((anchor, &scene.bodies[anchor]), (ball, &scene.bodies[ball]), Vec2::new(0.0, 0.0), Vec2::new(100.0, 0.0));
+        assert!(!rope.collide_connected);
+        scene.add_joint(Joint::Rope(rope));
+
+        let mut slack = false;
+        for _ in 0..240 {
+            scene.step(FRAME_TIME);
+            let length = scene.bodies[ball].position.magnitude();
+            assert!(length <= 100.0 + 2.0 * LINEAR_SLOP, "{}", length);
+            slack |= length < 90.0;
+        }
+        // The rope went slack at some point, it only pulls while taut
+        assert!(slack);
+    }
+
+    #[test]
+    fn pulley_keeps_total_length() {
+        let mut scene = Scene::new();
+        let (ground_a, ground_b) = (Vec2::new(-50.0, -100.0), Vec2::new(50.0, -100.0));
+        let a = scene.add(Body::new(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(-50.0, 0.0)));
+        let b = scene.add(Body::with_density(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(50.0, 20.0), 2.0));
+        let ratio = 1.5;
+
+        let pulley = PulleyJoint::new((a, &scene.bodies[a]), (b, &scene.bodies[b]), ground_a, ground_b,
+                                      Vec2::new(-50.0, -10.0), Vec2::new(50.0, 10.0), ratio);
+        scene.add_joint(Joint::Pulley(pulley));
+
+        let total = |scene: &Scene| {
+            let anchor_a = scene.bodies[a].world_point(Vec2::new(0.0, -10.0));
+            let anchor_b = scene.bodies[b].world_point(Vec2::new(0.0, -10.0));
+            (anchor_a - ground_a).magnitude() + ratio * (anchor_b - ground_b).magnitude()
+        };
+        let start = total(&scene);
+        let start_b = scene.bodies[b].position;
+
+        for _ in 0..60 {
+            scene.step(FRAME_TIME);
+            assert!((total(&scene) - start).abs() < 1.0, "{} != {}", total(&scene), start);
+        }
+        // The heavier side went down and pulled the other one up
+        assert!(scene.bodies[b].position.y > start_b.y + 10.0);
+        assert!(scene.bodies[a].position.y < 0.0);
+    }
+
+    #[test]
+    fn gear_keeps_ratio() {
+        let mut scene = Scene::new();
+        let (axle_a, axle_b) = (ground(&mut scene, Vec2::new(0.0, 0.0)), ground(&mut scene, Vec2::new(100.0, 0.0)));
+        let wheel_a = scene.add(Body::new(Shape::Circle { radius: 20.0 }, Vec2::new(0.0, 0.0)));
+        let wheel_b = scene.add(Body::new(Shape::Circle { radius: 40.0 }, Vec2::new(100.0, 0.0)));
+        let ratio = 2.0;
+
+        let hinge_a = Joint::Revolute(RevoluteJoint::new((axle_a, &scene.bodies[axle_a]), (wheel_a, &scene.bodies[wheel_a]), Vec2::new(0.0, 0.0)));
+        let hinge_b = Joint::Revolute(RevoluteJoint::new((axle_b, &scene.bodies[axle_b]), (wheel_b, &scene.bodies[wheel_b]), Vec2::new(100.0, 0.0)));
+        let gear = GearJoint::new(&hinge_a, &hinge_b, &scene.bodies, ratio).unwrap();
+        scene.add_joint(hinge_a);
+        scene.add_joint(hinge_b);
+        scene.add_joint(Joint::Gear(gear));
+
+        scene.bodies[wheel_a].angular_velocity = 3.0;
+        for _ in 0..120 {
+            scene.step(FRAME_TIME);
+            let coordinate = scene.bodies[wheel_a].orient.0 + ratio * scene.bodies[wheel_b].orient.0;
+            assert!(coordinate.abs() < ANGULAR_SLOP, "{}", coordinate);
+        }
+        assert!(scene.bodies[wheel_a].orient.0.abs() > 1.0);
+    }
+
+    #[test]
+    fn gear_couples_rack_and_pinion() {
+        let mut scene = Scene::new();
+        let axle = ground(&mut scene, Vec2::new(0.0, 0.0));
+        let rail = ground(&mut scene, Vec2::new(0.0, 100.0));
+        let pinion = scene.add(Body::new(Shape::Circle { radius: 20.0 }, Vec2::new(0.0, 0.0)));
+        let rack = scene.add(Body::new(Shape::rect(Vec2::new(60.0, 5.0)), Vec2::new(0.0, 100.0)));
+        let ratio = 1.0 / 20.0;
+
+        let hinge = Joint::Revolute(RevoluteJoint::new((axle, &scene.bodies[axle]), (pinion, &scene.bodies[pinion]), Vec2::new(0.0, 0.0)));
+        let slider = Joint::Prismatic(PrismaticJoint::new((rail, &scene.bodies[rail]), (rack, &scene.bodies[rack]), Vec2::new(0.0, 100.0), Vec2::new(1.0, 0.0)));
+        let gear = GearJoint::new(&hinge, &slider, &scene.bodies, ratio).unwrap();
+        scene.add_joint(hinge);
+        scene.add_joint(slider);
+        scene.add_joint(Joint::Gear(gear));
+
+        scene.bodies[rack].velocity = Vec2::new(40.0, 0.0);
+        for _ in 0..120 {
+            scene.step(FRAME_TIME);
+            let coordinate = scene.bodies[pinion].orient.0 + ratio * scene.bodies[rack].position.x;
+            assert!(coordinate.abs() < ANGULAR_SLOP, "{}", coordinate);
+        }
+        assert!(scene.bodies[rack].position.x.abs() > 10.0);
+    }
+
+    // A box hanging from a static pivot, returns the scene, the box and the joint
+    fn hanging_box(break_force: Option<Real>) -> (Scene, BodyIndex, JointHandle) {
+        let mut scene = Scene::new();
+        let pivot = ground(&mut scene, Vec2::new(0.0, 0.0));
+        let crate_ = scene.add(Body::new(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 30.0)));
+        let mut hinge = RevoluteJoint::new((pivot, &scene.bodies[pivot]), (crate_, &scene.bodies[crate_]), Vec2::new(0.0, 0.0));
+        hinge.break_force = break_force;
+        let handle = scene.add_joint(Joint::Revolute(hinge));
+        (scene, crate_, handle)
+    }
+
+    #[test]
+    fn reaction_carries_the_load() {
+        let (mut scene, crate_, handle) = hanging_box(None);
+        for _ in 0..60 {
+            scene.step(FRAME_TIME);
+        }
+
+        // The pivot pulls the box up with its weight, y points down
+        let weight = scene.bodies[crate_].mass * GRAVITY[1];
+        let (force, _) = scene.joint_reaction(handle).unwrap();
+        assert!(force.x.abs() < 0.01 * weight, "{:?}", force);
+        assert!((force.y + weight).abs() < 0.01 * weight, "{:?} != {}", force, -weight);
+    }
+
+    #[test]
+    fn joint_breaks_past_its_threshold() {
+        let mass = Body::new(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 0.0)).mass;
+        let (mut scene, crate_, handle) = hanging_box(Some(0.5 * mass * GRAVITY[1]));
+
+        let mut broken = vec![];
+        for _ in 0..30 {
+            scene.step(FRAME_TIME);
+            broken.extend(scene.take_events().into_iter().map(|event| match event {
+                Event::JointBroken { handle, force, .. } => (handle, force),
+            }));
+        }
+
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].0, handle);
+        assert!(broken[0].1.magnitude() > 0.5 * mass * GRAVITY[1]);
+        assert!(!scene.joints.contains(handle));
+        assert!(scene.joint_reaction(handle).is_none());
+        // Nothing holds the box anymore
+        assert!(scene.bodies[crate_].position.y > 40.0);
+    }
+
+    #[test]
+    fn joint_holds_below_its_threshold() {
+        let mass = Body::new(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(0.0, 0.0)).mass;
+        let (mut scene, _, handle) = hanging_box(Some(2.0 * mass * GRAVITY[1]));
+
+        for _ in 0..30 {
+            scene.step(FRAME_TIME);
+        }
+        assert!(scene.take_events().is_empty());
+        assert!(scene.joints.contains(handle));
+    }
+}
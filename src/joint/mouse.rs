@@ -0,0 +1,111 @@
+use super::super::types::{Vec2, Mat2, Real, PI};
+use super::super::body::Body;
+use super::super::scene::BodyIndex;
+use super::super::operations::cross_real_vector;
+use super::{point_inv_mass, invert_free22};
+use cgmath::{InnerSpace, Zero};
+
+// Soft constraint that pulls a point on a body towards a world space target,
+// limited by max_force.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct MouseJoint {
+    pub body: BodyIndex,
+    pub local_anchor: Vec2,
+    pub target: Vec2,
+    pub max_force: Real,
+    pub frequency: Real,
+    pub damping_ratio: Real,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    impulse: Vec2,
+    r: Vec2,
+    mass: Mat2,
+    c: Vec2,
+    gamma: Real,
+    delta: Real,
+}
+
+impl MouseJoint {
+    // b2MouseJointDef, grabbing the body at target
+    pub fn new((index, body): (BodyIndex, &Body), target: Vec2, max_force: Real) -> Self {
+        MouseJoint {
+            body: index,
+            local_anchor: body.local_point(target),
+            target: target,
+            max_force: max_force,
+            frequency: 5.0,
+            damping_ratio: 0.7,
+            break_force: None,
+            break_torque: None,
+
+            impulse: Vec2::zero(),
+            r: Vec2::zero(),
+            mass: Mat2::zero(),
+            c: Vec2::zero(),
+            gamma: 0.0,
+            delta: 0.0,
+        }
+    }
+
+    // b2MouseJoint::GetReactionForce
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        self.impulse * inv_delta
+    }
+
+    // b2MouseJoint::GetReactionTorque
+    pub fn reaction_torque(&self, _: Real) -> Real {
+        0.0
+    }
+
+    // b2MouseJoint::InitVelocityConstraints
+    pub fn init_velocity(&mut self, bodies: &mut [Body], delta: Real) {
+        let body = &mut bodies[self.body];
+
+        let omega = 2.0 * PI * self.frequency;
+        let d = 2.0 * body.mass * self.damping_ratio * omega;
+        let k = body.mass * omega * omega;
+
+        self.delta = delta;
+        self.gamma = delta * (d + delta * k);
+        if self.gamma != 0.0 {
+            self.gamma = 1.0 / self.gamma;
+        }
+        let beta = delta * k * self.gamma;
+
+        self.r = body.rotation() * self.local_anchor;
+
+        let k = point_inv_mass(body, self.r);
+        self.mass = invert_free22(&k, self.gamma);
+
+        self.c = (body.position + self.r - self.target) * beta;
+
+        // Cheat with some damping
+        body.angular_velocity *= 0.98;
+
+        body.apply_impulse(self.impulse, self.r);
+    }
+
+    // b2MouseJoint::SolveVelocityConstraints
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let body = &mut bodies[self.body];
+
+        let c_dot = body.velocity + cross_real_vector(body.angular_velocity, self.r);
+        let mut impulse = self.mass * -(c_dot + self.c + self.impulse * self.gamma);
+
+        let old_impulse = self.impulse;
+        self.impulse += impulse;
+        let max_impulse = self.delta * self.max_force;
+        if self.impulse.magnitude2() > max_impulse * max_impulse {
+            self.impulse *= max_impulse / self.impulse.magnitude();
+        }
+        impulse = self.impulse - old_impulse;
+
+        body.apply_impulse(impulse, self.r);
+    }
+
+    pub fn solve_position(&mut self, _: &mut [Body]) -> bool {
+        true
+    }
+}
@@ -0,0 +1,174 @@
+use super::super::types::{Vec2, Mat2, Real};
+use super::super::body::Body;
+use super::super::scene::BodyIndex;
+use super::super::operations::{cross_vectors, cross_real_vector};
+use super::{move_body, invert_free22, LINEAR_SLOP, ANGULAR_SLOP};
+use cgmath::{InnerSpace, Matrix, Zero, dot};
+
+// Lets body b slide along an axis fixed in body a, with no relative rotation.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PrismaticJoint {
+    pub bodies: (BodyIndex, BodyIndex),
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub local_axis_a: Vec2,
+    pub reference_angle: Real,
+    pub collide_connected: bool,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    impulse: Vec2,
+    r_a: Vec2,
+    r_b: Vec2,
+    perp: Vec2,
+    s1: Real,
+    s2: Real,
+    mass: Mat2,
+}
+
+impl PrismaticJoint {
+    // b2PrismaticJointDef::Initialize
+    pub fn new((i_a, body_a): (BodyIndex, &Body), (i_b, body_b): (BodyIndex, &Body), anchor: Vec2, axis: Vec2) -> Self {
+        PrismaticJoint {
+            bodies: (i_a, i_b),
+            local_anchor_a: body_a.local_point(anchor),
+            local_anchor_b: body_b.local_point(anchor),
+            local_axis_a: body_a.rotation().transpose() * axis.normalize(),
+            reference_angle: body_b.orient.0 - body_a.orient.0,
+            collide_connected: false,
+            break_force: None,
+            break_torque: None,
+
+            impulse: Vec2::zero(),
+            r_a: Vec2::zero(),
+            r_b: Vec2::zero(),
+            perp: Vec2::zero(),
+            s1: 0.0,
+            s2: 0.0,
+            mass: Mat2::zero(),
+        }
+    }
+
+    // Returns (r_a, r_b, perp, s1, s2) for the current body positions
+    fn jacobian(&self, body_a: &Body, body_b: &Body) -> (Vec2, Vec2, Vec2, Real, Real) {
+        let r_a = body_a.rotation() * self.local_anchor_a;
+        let r_b = body_b.rotation() * self.local_anchor_b;
+        let d = body_b.position + r_b - body_a.position - r_a;
+
+        let perp = body_a.rotation() * cross_real_vector(1.0, self.local_axis_a);
+        let s1 = cross_vectors(d + r_a, perp);
+        let s2 = cross_vectors(r_b, perp);
+
+        (r_a, r_b, perp, s1, s2)
+    }
+
+    fn effective_mass(body_a: &Body, body_b: &Body, perp: Vec2, s1: Real, s2: Real) -> Mat2 {
+        let (m_a, m_b) = (body_a.inv_mass_along(perp), body_b.inv_mass_along(perp));
+        let (i_a, i_b) = (body_a.inv_inertia, body_b.inv_inertia);
+
+        let k11 = m_a + m_b + i_a * s1 * s1 + i_b * s2 * s2;
+        let k12 = i_a * s1 + i_b * s2;
+        let mut k22 = i_a + i_b;
+        if k22 == 0.0 {
+            // For bodies with fixed rotation
+            k22 = 1.0;
+        }
+
+        Mat2::new(k11, k12, k12, k22)
+    }
+
+    // b2PrismaticJoint::GetReactionForce
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        self.perp * self.impulse.x * inv_delta
+    }
+
+    // b2PrismaticJoint::GetReactionTorque
+    pub fn reaction_torque(&self, inv_delta: Real) -> Real {
+        self.impulse.y * inv_delta
+    }
+
+    // b2PrismaticJoint::InitVelocityConstraints
+    pub fn init_velocity(&mut self, bodies: &mut [Body], _: Real) {
+        let (index_a, index_b) = self.bodies;
+
+        let (r_a, r_b, perp, s1, s2) = self.jacobian(&bodies[index_a], &bodies[index_b]);
+        self.r_a = r_a;
+        self.r_b = r_b;
+        self.perp = perp;
+        self.s1 = s1;
+        self.s2 = s2;
+
+        let k = Self::effective_mass(&bodies[index_a], &bodies[index_b], perp, s1, s2);
+        self.mass = invert_free22(&k, 0.0);
+
+        let impulse = self.impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2PrismaticJoint::SolveVelocityConstraints
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let c_dot = {
+            let (body_a, body_b) = (&bodies[self.bodies.0], &bodies[self.bodies.1]);
+            Vec2::new(
+                dot(self.perp, body_b.velocity - body_a.velocity) +
+                self.s2 * body_b.angular_velocity - self.s1 * body_a.angular_velocity,
+                body_b.angular_velocity - body_a.angular_velocity,
+            )
+        };
+
+        let impulse = self.mass * -c_dot;
+        self.impulse += impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2PrismaticJoint::SolvePositionConstraints
+    pub fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (index_a, index_b) = self.bodies;
+
+        let (r_a, r_b, perp, s1, s2) = self.jacobian(&bodies[index_a], &bodies[index_b]);
+        let d = bodies[index_b].position + r_b - bodies[index_a].position - r_a;
+
+        let c = Vec2::new(
+            dot(perp, d),
+            bodies[index_b].orient.0 - bodies[index_a].orient.0 - self.reference_angle,
+        );
+
+        let k = Self::effective_mass(&bodies[index_a], &bodies[index_b], perp, s1, s2);
+        let impulse = invert_free22(&k, 0.0) * -c;
+
+        let p = impulse.x * perp;
+        let l_a = impulse.x * s1 + impulse.y;
+        let l_b = impulse.x * s2 + impulse.y;
+
+        {
+            let body_a = &mut bodies[index_a];
+            let (m, i) = (body_a.inv_mass, body_a.inv_inertia);
+            move_body(body_a, -m * p, -i * l_a);
+        }
+        {
+            let body_b = &mut bodies[index_b];
+            let (m, i) = (body_b.inv_mass, body_b.inv_inertia);
+            move_body(body_b, m * p, i * l_b);
+        }
+
+        c.x.abs() <= LINEAR_SLOP && c.y.abs() <= ANGULAR_SLOP
+    }
+
+    fn apply(&self, bodies: &mut [Body], impulse: Vec2) {
+        let p = impulse.x * self.perp;
+        let l_a = impulse.x * self.s1 + impulse.y;
+        let l_b = impulse.x * self.s2 + impulse.y;
+
+        {
+            let body_a = &mut bodies[self.bodies.0];
+            body_a.apply_impulse(-p, Vec2::zero());
+            body_a.apply_angular_impulse(-l_a);
+        }
+        {
+            let body_b = &mut bodies[self.bodies.1];
+            body_b.apply_impulse(p, Vec2::zero());
+            body_b.apply_angular_impulse(l_b);
+        }
+    }
+}
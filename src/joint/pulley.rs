@@ -0,0 +1,162 @@
+use super::super::types::{Vec2, Real};
+use super::super::body::Body;
+use super::super::scene::BodyIndex;
+use super::super::operations::{cross_vectors, cross_real_vector};
+use super::{move_body, LINEAR_SLOP};
+use cgmath::{InnerSpace, Zero, dot};
+
+// Connects two bodies with a rope running over two fixed ground anchors, so that
+// length_a + ratio * length_b stays constant.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct PulleyJoint {
+    pub bodies: (BodyIndex, BodyIndex),
+    pub ground_anchor_a: Vec2,
+    pub ground_anchor_b: Vec2,
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub length_a: Real,
+    pub length_b: Real,
+    pub ratio: Real,
+    pub collide_connected: bool,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    constant: Real,
+    impulse: Real,
+    r_a: Vec2,
+    r_b: Vec2,
+    u_a: Vec2,
+    u_b: Vec2,
+    mass: Real,
+}
+
+impl PulleyJoint {
+    // b2PulleyJointDef::Initialize
+    pub fn new((i_a, body_a): (BodyIndex, &Body), (i_b, body_b): (BodyIndex, &Body),
+               ground_anchor_a: Vec2, ground_anchor_b: Vec2,
+               anchor_a: Vec2, anchor_b: Vec2,
+               ratio: Real) -> Self {
+        assert!(ratio > ::std::f32::EPSILON);
+
+        let length_a = (anchor_a - ground_anchor_a).magnitude();
+        let length_b = (anchor_b - ground_anchor_b).magnitude();
+
+        PulleyJoint {
+            bodies: (i_a, i_b),
+            ground_anchor_a: ground_anchor_a,
+            ground_anchor_b: ground_anchor_b,
+            local_anchor_a: body_a.local_point(anchor_a),
+            local_anchor_b: body_b.local_point(anchor_b),
+            length_a: length_a,
+            length_b: length_b,
+            ratio: ratio,
+            // b2PulleyJointDef is the one joint definition that collides by default
+            collide_connected: true,
+            break_force: None,
+            break_torque: None,
+
+            constant: length_a + ratio * length_b,
+            impulse: 0.0,
+            r_a: Vec2::zero(),
+            r_b: Vec2::zero(),
+            u_a: Vec2::zero(),
+            u_b: Vec2::zero(),
+            mass: 0.0,
+        }
+    }
+
+    // Returns (r_a, r_b, u_a, u_b, length_a, length_b, inverse effective mass)
+    fn jacobian(&self, body_a: &Body, body_b: &Body) -> (Vec2, Vec2, Vec2, Vec2, Real, Real, Real) {
+        let r_a = body_a.rotation() * self.local_anchor_a;
+        let r_b = body_b.rotation() * self.local_anchor_b;
+
+        let mut u_a = body_a.position + r_a - self.ground_anchor_a;
+        let mut u_b = body_b.position + r_b - self.ground_anchor_b;
+
+        let length_a = u_a.magnitude();
+        let length_b = u_b.magnitude();
+
+        u_a = if length_a > 10.0 * LINEAR_SLOP { u_a / length_a } else { Vec2::zero() };
+        u_b = if length_b > 10.0 * LINEAR_SLOP { u_b / length_b } else { Vec2::zero() };
+
+        let ru_a = cross_vectors(r_a, u_a);
+        let ru_b = cross_vectors(r_b, u_b);
+
+        let m_a = body_a.inv_mass_along(u_a) + body_a.inv_inertia * ru_a * ru_a;
+        let m_b = body_b.inv_mass_along(u_b) + body_b.inv_inertia * ru_b * ru_b;
+
+        (r_a, r_b, u_a, u_b, length_a, length_b, m_a + self.ratio * self.ratio * m_b)
+    }
+
+    // b2PulleyJoint::GetReactionForce
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        self.u_b * self.impulse * inv_delta
+    }
+
+    // b2PulleyJoint::GetReactionTorque
+    pub fn reaction_torque(&self, _: Real) -> Real {
+        0.0
+    }
+
+    // b2PulleyJoint::InitVelocityConstraints
+    pub fn init_velocity(&mut self, bodies: &mut [Body], _: Real) {
+        let (r_a, r_b, u_a, u_b, _, _, inv_mass) = self.jacobian(&bodies[self.bodies.0], &bodies[self.bodies.1]);
+
+        self.r_a = r_a;
+        self.r_b = r_b;
+        self.u_a = u_a;
+        self.u_b = u_b;
+        self.mass = if inv_mass > 0.0 { 1.0 / inv_mass } else { 0.0 };
+
+        let impulse = self.impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2PulleyJoint::SolveVelocityConstraints
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let c_dot = {
+            let (body_a, body_b) = (&bodies[self.bodies.0], &bodies[self.bodies.1]);
+            let vp_a = body_a.velocity + cross_real_vector(body_a.angular_velocity, self.r_a);
+            let vp_b = body_b.velocity + cross_real_vector(body_b.angular_velocity, self.r_b);
+            -dot(self.u_a, vp_a) - self.ratio * dot(self.u_b, vp_b)
+        };
+
+        let impulse = -self.mass * c_dot;
+        self.impulse += impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2PulleyJoint::SolvePositionConstraints
+    pub fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (index_a, index_b) = self.bodies;
+        let (r_a, r_b, u_a, u_b, length_a, length_b, inv_mass) = self.jacobian(&bodies[index_a], &bodies[index_b]);
+
+        let mass = if inv_mass > 0.0 { 1.0 / inv_mass } else { 0.0 };
+        let c = self.constant - length_a - self.ratio * length_b;
+        let impulse = -mass * c;
+
+        let p_a = -impulse * u_a;
+        let p_b = -self.ratio * impulse * u_b;
+
+        {
+            let body_a = &mut bodies[index_a];
+            let (m, i) = (body_a.inv_mass, body_a.inv_inertia);
+            move_body(body_a, m * p_a, i * cross_vectors(r_a, p_a));
+        }
+        {
+            let body_b = &mut bodies[index_b];
+            let (m, i) = (body_b.inv_mass, body_b.inv_inertia);
+            move_body(body_b, m * p_b, i * cross_vectors(r_b, p_b));
+        }
+
+        c.abs() < LINEAR_SLOP
+    }
+
+    fn apply(&self, bodies: &mut [Body], impulse: Real) {
+        let p_a = -impulse * self.u_a;
+        let p_b = -self.ratio * impulse * self.u_b;
+        bodies[self.bodies.0].apply_impulse(p_a, self.r_a);
+        bodies[self.bodies.1].apply_impulse(p_b, self.r_b);
+    }
+}
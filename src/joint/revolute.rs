@@ -0,0 +1,117 @@
+use super::super::types::{Vec2, Mat2, Real};
+use super::super::body::Body;
+use super::super::scene::BodyIndex;
+use super::super::operations::{cross_vectors, cross_real_vector};
+use super::{move_body, point_inv_mass, invert_free22, LINEAR_SLOP};
+use cgmath::{InnerSpace, Zero};
+
+// Pins two bodies together at a shared anchor, leaving the relative rotation free.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct RevoluteJoint {
+    pub bodies: (BodyIndex, BodyIndex),
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub reference_angle: Real,
+    pub collide_connected: bool,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    impulse: Vec2,
+    r_a: Vec2,
+    r_b: Vec2,
+    mass: Mat2,
+}
+
+impl RevoluteJoint {
+    // b2RevoluteJointDef::Initialize
+    pub fn new((i_a, body_a): (BodyIndex, &Body), (i_b, body_b): (BodyIndex, &Body), anchor: Vec2) -> Self {
+        RevoluteJoint {
+            bodies: (i_a, i_b),
+            local_anchor_a: body_a.local_point(anchor),
+            local_anchor_b: body_b.local_point(anchor),
+            reference_angle: body_b.orient.0 - body_a.orient.0,
+            collide_connected: false,
+            break_force: None,
+            break_torque: None,
+
+            impulse: Vec2::zero(),
+            r_a: Vec2::zero(),
+            r_b: Vec2::zero(),
+            mass: Mat2::zero(),
+        }
+    }
+
+    fn effective_mass(body_a: &Body, body_b: &Body, r_a: Vec2, r_b: Vec2) -> Mat2 {
+        point_inv_mass(body_a, r_a) + point_inv_mass(body_b, r_b)
+    }
+
+    // b2RevoluteJoint::GetReactionForce
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        self.impulse * inv_delta
+    }
+
+    // b2RevoluteJoint::GetReactionTorque
+    pub fn reaction_torque(&self, _: Real) -> Real {
+        0.0
+    }
+
+    // b2RevoluteJoint::InitVelocityConstraints
+    pub fn init_velocity(&mut self, bodies: &mut [Body], _: Real) {
+        let (index_a, index_b) = self.bodies;
+
+        self.r_a = bodies[index_a].rotation() * self.local_anchor_a;
+        self.r_b = bodies[index_b].rotation() * self.local_anchor_b;
+
+        let k = Self::effective_mass(&bodies[index_a], &bodies[index_b], self.r_a, self.r_b);
+        self.mass = invert_free22(&k, 0.0);
+
+        let impulse = self.impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2RevoluteJoint::SolveVelocityConstraints
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let c_dot = {
+            let (body_a, body_b) = (&bodies[self.bodies.0], &bodies[self.bodies.1]);
+            body_b.velocity + cross_real_vector(body_b.angular_velocity, self.r_b) -
+            body_a.velocity - cross_real_vector(body_a.angular_velocity, self.r_a)
+        };
+
+        let impulse = self.mass * -c_dot;
+        self.impulse += impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2RevoluteJoint::SolvePositionConstraints
+    pub fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (index_a, index_b) = self.bodies;
+
+        let r_a = bodies[index_a].rotation() * self.local_anchor_a;
+        let r_b = bodies[index_b].rotation() * self.local_anchor_b;
+
+        let c = bodies[index_b].position + r_b - bodies[index_a].position - r_a;
+        let position_error = c.magnitude();
+
+        let k = Self::effective_mass(&bodies[index_a], &bodies[index_b], r_a, r_b);
+        let impulse = invert_free22(&k, 0.0) * -c;
+
+        {
+            let body_a = &mut bodies[index_a];
+            let (m, i) = (body_a.inv_mass, body_a.inv_inertia);
+            move_body(body_a, -m * impulse, -i * cross_vectors(r_a, impulse));
+        }
+        {
+            let body_b = &mut bodies[index_b];
+            let (m, i) = (body_b.inv_mass, body_b.inv_inertia);
+            move_body(body_b, m * impulse, i * cross_vectors(r_b, impulse));
+        }
+
+        position_error <= LINEAR_SLOP
+    }
+
+    fn apply(&self, bodies: &mut [Body], p: Vec2) {
+        bodies[self.bodies.0].apply_impulse(-p, self.r_a);
+        bodies[self.bodies.1].apply_impulse(p, self.r_b);
+    }
+}
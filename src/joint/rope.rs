@@ -0,0 +1,147 @@
+use super::super::types::{Vec2, Real};
+use super::super::body::Body;
+use super::super::scene::BodyIndex;
+use super::super::operations::{cross_vectors, cross_real_vector};
+use super::{move_body, LINEAR_SLOP, MAX_LINEAR_CORRECTION};
+use cgmath::{InnerSpace, Zero, dot};
+
+// Keeps two anchor points from getting further apart than max_length, like a rope.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct RopeJoint {
+    pub bodies: (BodyIndex, BodyIndex),
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub max_length: Real,
+    pub collide_connected: bool,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    impulse: Real,
+    r_a: Vec2,
+    r_b: Vec2,
+    u: Vec2,
+    length: Real,
+    mass: Real,
+    inv_delta: Real,
+}
+
+impl RopeJoint {
+    // The rope starts with max_length set to the distance between the anchors
+    pub fn new((i_a, body_a): (BodyIndex, &Body), (i_b, body_b): (BodyIndex, &Body), anchor_a: Vec2, anchor_b: Vec2) -> Self {
+        RopeJoint {
+            bodies: (i_a, i_b),
+            local_anchor_a: body_a.local_point(anchor_a),
+            local_anchor_b: body_b.local_point(anchor_b),
+            max_length: (anchor_b - anchor_a).magnitude(),
+            collide_connected: false,
+            break_force: None,
+            break_torque: None,
+
+            impulse: 0.0,
+            r_a: Vec2::zero(),
+            r_b: Vec2::zero(),
+            u: Vec2::zero(),
+            length: 0.0,
+            mass: 0.0,
+            inv_delta: 0.0,
+        }
+    }
+
+    // b2RopeJoint::GetReactionForce
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        self.u * self.impulse * inv_delta
+    }
+
+    // b2RopeJoint::GetReactionTorque
+    pub fn reaction_torque(&self, _: Real) -> Real {
+        0.0
+    }
+
+    // b2RopeJoint::InitVelocityConstraints
+    pub fn init_velocity(&mut self, bodies: &mut [Body], delta: Real) {
+        let (index_a, index_b) = self.bodies;
+        let (body_a, body_b) = (&bodies[index_a], &bodies[index_b]);
+
+        self.inv_delta = if delta > 0.0 { 1.0 / delta } else { 0.0 };
+        self.r_a = body_a.rotation() * self.local_anchor_a;
+        self.r_b = body_b.rotation() * self.local_anchor_b;
+        self.u = body_b.position + self.r_b - body_a.position - self.r_a;
+        self.length = self.u.magnitude();
+
+        if self.length > LINEAR_SLOP {
+            self.u /= self.length;
+        } else {
+            self.u = Vec2::zero();
+            self.mass = 0.0;
+            self.impulse = 0.0;
+            return
+        }
+
+        let cr_a = cross_vectors(self.r_a, self.u);
+        let cr_b = cross_vectors(self.r_b, self.u);
+        let inv_mass = body_a.inv_mass_along(self.u) + body_a.inv_inertia * cr_a * cr_a +
+                       body_b.inv_mass_along(self.u) + body_b.inv_inertia * cr_b * cr_b;
+        self.mass = if inv_mass != 0.0 { 1.0 / inv_mass } else { 0.0 };
+
+        let impulse = self.impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2RopeJoint::SolveVelocityConstraints
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let mut c_dot = {
+            let (body_a, body_b) = (&bodies[self.bodies.0], &bodies[self.bodies.1]);
+            let vp_a = body_a.velocity + cross_real_vector(body_a.angular_velocity, self.r_a);
+            let vp_b = body_b.velocity + cross_real_vector(body_b.angular_velocity, self.r_b);
+            dot(self.u, vp_b - vp_a)
+        };
+
+        // Predictive constraint, only push while the rope would become taut this step
+        let c = self.length - self.max_length;
+        if c < 0.0 {
+            c_dot += self.inv_delta * c;
+        }
+
+        let old_impulse = self.impulse;
+        self.impulse = (self.impulse - self.mass * c_dot).min(0.0);
+        let impulse = self.impulse - old_impulse;
+        self.apply(bodies, impulse);
+    }
+
+    // b2RopeJoint::SolvePositionConstraints
+    pub fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (index_a, index_b) = self.bodies;
+
+        let r_a = bodies[index_a].rotation() * self.local_anchor_a;
+        let r_b = bodies[index_b].rotation() * self.local_anchor_b;
+        let mut u = bodies[index_b].position + r_b - bodies[index_a].position - r_a;
+
+        let length = u.magnitude();
+        if length > LINEAR_SLOP {
+            u /= length;
+        }
+        let c = (length - self.max_length).max(0.0).min(MAX_LINEAR_CORRECTION);
+
+        let p = -self.mass * c * u;
+
+        {
+            let body_a = &mut bodies[index_a];
+            let (m, i) = (body_a.inv_mass, body_a.inv_inertia);
+            move_body(body_a, -m * p, -i * cross_vectors(r_a, p));
+        }
+        {
+            let body_b = &mut bodies[index_b];
+            let (m, i) = (body_b.inv_mass, body_b.inv_inertia);
+            move_body(body_b, m * p, i * cross_vectors(r_b, p));
+        }
+
+        length - self.max_length < LINEAR_SLOP
+    }
+
+    fn apply(&self, bodies: &mut [Body], impulse: Real) {
+        let p = impulse * self.u;
+        bodies[self.bodies.0].apply_impulse(-p, self.r_a);
+        bodies[self.bodies.1].apply_impulse(p, self.r_b);
+    }
+}
@@ -0,0 +1,213 @@
+use super::super::types::{Vec2, Vec3, Mat3, Real, PI};
+use super::super::body::Body;
+use super::super::scene::BodyIndex;
+use super::super::operations::{cross_vectors, cross_real_vector};
+use super::{move_body, point_inv_mass, upper_left, inverse22, inverse33, solve22, solve33, LINEAR_SLOP, ANGULAR_SLOP};
+use cgmath::{InnerSpace, Zero};
+
+// Locks the relative position and rotation of two bodies. With a non-zero
+// frequency the angular part behaves like a damped spring instead.
+#[derive(Clone)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct WeldJoint {
+    pub bodies: (BodyIndex, BodyIndex),
+    pub local_anchor_a: Vec2,
+    pub local_anchor_b: Vec2,
+    pub reference_angle: Real,
+    pub frequency: Real,
+    pub damping_ratio: Real,
+    pub collide_connected: bool,
+    pub break_force: Option<Real>,
+    pub break_torque: Option<Real>,
+
+    impulse: Vec3,
+    r_a: Vec2,
+    r_b: Vec2,
+    mass: Mat3,
+    gamma: Real,
+    bias: Real,
+}
+
+impl WeldJoint {
+    // b2WeldJointDef::Initialize
+    pub fn new((i_a, body_a): (BodyIndex, &Body), (i_b, body_b): (BodyIndex, &Body), anchor: Vec2) -> Self {
+        WeldJoint {
+            bodies: (i_a, i_b),
+            local_anchor_a: body_a.local_point(anchor),
+            local_anchor_b: body_b.local_point(anchor),
+            reference_angle: body_b.orient.0 - body_a.orient.0,
+            frequency: 0.0,
+            damping_ratio: 0.0,
+            collide_connected: false,
+            break_force: None,
+            break_torque: None,
+
+            impulse: Vec3::zero(),
+            r_a: Vec2::zero(),
+            r_b: Vec2::zero(),
+            mass: Mat3::zero(),
+            gamma: 0.0,
+            bias: 0.0,
+        }
+    }
+
+    pub fn with_softness(mut self, frequency: Real, damping_ratio: Real) -> Self {
+        self.frequency = frequency;
+        self.damping_ratio = damping_ratio;
+        self
+    }
+
+    fn effective_mass(&self, body_a: &Body, body_b: &Body, r_a: Vec2, r_b: Vec2) -> Mat3 {
+        let k = point_inv_mass(body_a, r_a) + point_inv_mass(body_b, r_b);
+        let (i_a, i_b) = (body_a.inv_inertia, body_b.inv_inertia);
+
+        Mat3::new(
+            k.x.x,
+            k.x.y,
+            -r_a.y * i_a - r_b.y * i_b,
+
+            k.y.x,
+            k.y.y,
+            r_a.x * i_a + r_b.x * i_b,
+
+            -r_a.y * i_a - r_b.y * i_b,
+            r_a.x * i_a + r_b.x * i_b,
+            i_a + i_b,
+        )
+    }
+
+    // b2WeldJoint::GetReactionForce
+    pub fn reaction_force(&self, inv_delta: Real) -> Vec2 {
+        Vec2::new(self.impulse.x, self.impulse.y) * inv_delta
+    }
+
+    // b2WeldJoint::GetReactionTorque
+    pub fn reaction_torque(&self, inv_delta: Real) -> Real {
+        self.impulse.z * inv_delta
+    }
+
+    // b2WeldJoint::InitVelocityConstraints
+    pub fn init_velocity(&mut self, bodies: &mut [Body], delta: Real) {
+        let (index_a, index_b) = self.bodies;
+
+        self.r_a = bodies[index_a].rotation() * self.local_anchor_a;
+        self.r_b = bodies[index_b].rotation() * self.local_anchor_b;
+
+        let k = self.effective_mass(&bodies[index_a], &bodies[index_b], self.r_a, self.r_b);
+
+        if self.frequency > 0.0 {
+            self.mass = inverse22(&k);
+
+            let mut inv_m = k.z.z;
+            let m = if inv_m > 0.0 { 1.0 / inv_m } else { 0.0 };
+
+            let c = bodies[index_b].orient.0 - bodies[index_a].orient.0 - self.reference_angle;
+            let omega = 2.0 * PI * self.frequency;
+            let d = 2.0 * m * self.damping_ratio * omega;
+            let spring = m * omega * omega;
+
+            self.gamma = delta * (d + delta * spring);
+            self.gamma = if self.gamma != 0.0 { 1.0 / self.gamma } else { 0.0 };
+            self.bias = c * delta * spring * self.gamma;
+
+            inv_m += self.gamma;
+            self.mass.z.z = if inv_m != 0.0 { 1.0 / inv_m } else { 0.0 };
+        } else if k.z.z == 0.0 {
+            self.mass = inverse22(&k);
+            self.gamma = 0.0;
+            self.bias = 0.0;
+        } else {
+            self.mass = inverse33(&k);
+            self.gamma = 0.0;
+            self.bias = 0.0;
+        }
+
+        // Warm start with the impulse from the last step
+        let p = Vec2::new(self.impulse.x, self.impulse.y);
+        let angular = self.impulse.z;
+        self.apply(bodies, p, angular);
+    }
+
+    // b2WeldJoint::SolveVelocityConstraints
+    pub fn solve_velocity(&mut self, bodies: &mut [Body]) {
+        let (index_a, index_b) = self.bodies;
+
+        if self.frequency > 0.0 {
+            let c_dot2 = bodies[index_b].angular_velocity - bodies[index_a].angular_velocity;
+            let impulse2 = -self.mass.z.z * (c_dot2 + self.bias + self.gamma * self.impulse.z);
+            self.impulse.z += impulse2;
+            self.apply(bodies, Vec2::zero(), impulse2);
+
+            let c_dot1 = self.relative_velocity(bodies);
+            let impulse1 = -(upper_left(&self.mass) * c_dot1);
+            self.impulse.x += impulse1.x;
+            self.impulse.y += impulse1.y;
+            self.apply(bodies, impulse1, 0.0);
+        } else {
+            let c_dot1 = self.relative_velocity(bodies);
+            let c_dot2 = bodies[index_b].angular_velocity - bodies[index_a].angular_velocity;
+
+            let impulse = -(self.mass * Vec3::new(c_dot1.x, c_dot1.y, c_dot2));
+            self.impulse += impulse;
+            self.apply(bodies, Vec2::new(impulse.x, impulse.y), impulse.z);
+        }
+    }
+
+    // b2WeldJoint::SolvePositionConstraints
+    pub fn solve_position(&mut self, bodies: &mut [Body]) -> bool {
+        let (index_a, index_b) = self.bodies;
+
+        let r_a = bodies[index_a].rotation() * self.local_anchor_a;
+        let r_b = bodies[index_b].rotation() * self.local_anchor_b;
+        let k = self.effective_mass(&bodies[index_a], &bodies[index_b], r_a, r_b);
+
+        let c1 = bodies[index_b].position + r_b - bodies[index_a].position - r_a;
+        let position_error = c1.magnitude();
+
+        let (p, angular, angular_error) = if self.frequency > 0.0 {
+            (-solve22(&k, c1), 0.0, 0.0)
+        } else {
+            let c2 = bodies[index_b].orient.0 - bodies[index_a].orient.0 - self.reference_angle;
+
+            if k.z.z > 0.0 {
+                let impulse = -solve33(&k, Vec3::new(c1.x, c1.y, c2));
+                (Vec2::new(impulse.x, impulse.y), impulse.z, c2.abs())
+            } else {
+                (-solve22(&k, c1), 0.0, c2.abs())
+            }
+        };
+
+        {
+            let body_a = &mut bodies[index_a];
+            let (m, i) = (body_a.inv_mass, body_a.inv_inertia);
+            move_body(body_a, -m * p, -i * (cross_vectors(r_a, p) + angular));
+        }
+        {
+            let body_b = &mut bodies[index_b];
+            let (m, i) = (body_b.inv_mass, body_b.inv_inertia);
+            move_body(body_b, m * p, i * (cross_vectors(r_b, p) + angular));
+        }
+
+        position_error <= LINEAR_SLOP && angular_error <= ANGULAR_SLOP
+    }
+
+    fn relative_velocity(&self, bodies: &[Body]) -> Vec2 {
+        let (body_a, body_b) = (&bodies[self.bodies.0], &bodies[self.bodies.1]);
+
+        body_b.velocity + cross_real_vector(body_b.angular_velocity, self.r_b) -
+        body_a.velocity - cross_real_vector(body_a.angular_velocity, self.r_a)
+    }
+
+    fn apply(&self, bodies: &mut [Body], p: Vec2, angular: Real) {
+        {
+            let body_a = &mut bodies[self.bodies.0];
+            body_a.apply_impulse(-p, self.r_a);
+            body_a.apply_angular_impulse(-angular);
+        }
+        {
+            let body_b = &mut bodies[self.bodies.1];
+            body_b.apply_impulse(p, self.r_b);
+            body_b.apply_angular_impulse(angular);
+        }
+    }
+}
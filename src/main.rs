@@ -1,20 +1,32 @@
 extern crate cgmath;
 extern crate ggez;
 extern crate rayon;
+#[cfg(feature = "serialize")]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(all(test, feature = "serialize"))]
+extern crate serde_json;
 
 mod types;
 use types::{Vec2, Mat2, Real};
 
 mod body;
-use body::{Body, Shape};
+use body::{Body, BodyType, Shape, Fixture, Transform};
 
 mod operations;
 mod collision;
+mod joint;
+use joint::{Joint, JointHandle, MouseJoint};
+
+mod integrator;
+mod force;
+mod nbody;
 mod scene;
 use scene::Scene;
 
 use ggez::conf;
 use ggez::event;
+use ggez::event::{MouseButton, MouseState};
 use ggez::{Context, GameResult};
 use ggez::graphics;
 use ggez::graphics::{DrawMode, Point};
@@ -23,20 +35,22 @@ use cgmath::{Rad, Deg};
 
 struct MainState {
     scene: Scene,
-    time: Duration,
     spawn_timer: Duration,
     spawn_count: u32,
     dt: Duration,
+    alpha: Real,
+    mouse_joint: Option<JointHandle>,
 }
 
 impl MainState {
     fn new(_: &mut Context) -> GameResult<MainState> {
         let s = MainState {
             scene: Scene::new(),
-            time: Duration::from_secs(0),
             dt: Duration::from_secs(0),
             spawn_timer: Duration::from_secs(0),
             spawn_count: 0,
+            alpha: 0.0,
+            mouse_joint: None,
         };
 
         Ok(s)
@@ -65,10 +79,79 @@ fn create_rect<T: Into<Rad<f32>>>(hw: Real, hh: Real, x: Real, y: Real, orientat
     b
 }
 
+fn create_capsule<T: Into<Rad<f32>>>(half_height: Real, radius: Real, x: Real, y: Real, orientation: T, dynamic: bool) -> Body {
+    let shape = Shape::Capsule { half_height, radius };
+    let mut b = Body::new(shape, Vec2::new(x, y));
+    b.set_orient(orientation);
+    if !dynamic { b.set_static() }
+    b
+}
+
+fn draw_shape(ctx: &mut Context, shape: &Shape, transform: &Transform, is_static: bool) -> GameResult<()> {
+    match shape {
+        &Shape::Circle { radius } => {
+            if is_static {
+                graphics::set_color(ctx, graphics::WHITE)?;
+            } else {
+                match radius {
+                    10.0 => graphics::set_color(ctx, graphics::Color::new(200./255.,101./255.,230./255.,200./255.))?,
+                    30.0 => graphics::set_color(ctx, graphics::Color::new(255./255.,0./255.,0./255.,150./255.))?,
+                    _ => graphics::set_color(ctx, graphics::Color::new(100./255.,101./255.,230./255.,200./255.))?,
+                }
+                //graphics::circle(ctx, DrawMode::Fill, Point { x: body.position.x, y: body.position.y }, radius, 6)?;
+            }
+            graphics::circle(ctx, DrawMode::Fill, Point { x: transform.position.x, y: transform.position.y }, radius, 32)?;
+        }
+        &Shape::Polygon { .. } => {
+            let a = -transform.orient.0;
+            let rotation_matrix = Mat2::new(a.cos(), -a.sin(), a.sin(), a.cos());
+
+            // The outline includes the rounded corners
+            graphics::polygon(ctx, DrawMode::Fill, 
+                shape.outline().into_iter()
+                .map(|v| rotation_matrix * v)
+                .map(|v| v + transform.position)
+                .map(|v| Point { x: v.x, y: v.y })
+                .collect::<Vec<_>>().as_ref())?;
+        }
+        &Shape::Capsule { .. } => {
+            if is_static {
+                graphics::set_color(ctx, graphics::WHITE)?;
+            } else {
+                graphics::set_color(ctx, graphics::Color::new(230./255.,160./255.,60./255.,200./255.))?;
+            }
+
+            graphics::polygon(ctx, DrawMode::Fill,
+                shape.outline().into_iter()
+                .map(|v| transform.world_point(v))
+                .map(|v| Point { x: v.x, y: v.y })
+                .collect::<Vec<_>>().as_ref())?;
+        }
+        &Shape::Edge { .. } | &Shape::Chain { .. } => {
+            graphics::set_color(ctx, graphics::WHITE)?;
+
+            for edge in shape.edges() {
+                let v1 = transform.world_point(edge.vertex1);
+                let v2 = transform.world_point(edge.vertex2);
+                graphics::line(ctx, &[Point { x: v1.x, y: v1.y }, Point { x: v2.x, y: v2.y }])?;
+            }
+        }
+        &Shape::Compound { ref fixtures } => {
+            for fixture in fixtures {
+                let transform = Transform {
+                    position: transform.world_point(fixture.offset),
+                    orient: transform.orient + fixture.rotation,
+                };
+                draw_shape(ctx, &fixture.shape, &transform, is_static)?;
+            }
+        }
+    }
+    Ok(())
+}
+
 impl event::EventHandler for MainState {
     fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
         self.dt = dt;
-        self.time += dt;
         self.spawn_timer += dt;
 
         if self.spawn_timer >= Duration::from_millis(200) /*&& self.spawn_count < 100*/ {
@@ -91,49 +174,69 @@ impl event::EventHandler for MainState {
             self.scene.add(circle);
         }
 
-        let frame_time = Duration::from_millis((scene::FRAME_TIME * 1000.0) as u64);
-        let dt_float = scene::FRAME_TIME;
-        while self.time >= frame_time {
-            self.time -= frame_time;
+        let frame_delta = dt.as_secs() as Real + dt.subsec_nanos() as Real / 1_000_000_000.0;
+        self.alpha = self.scene.update(frame_delta);
 
-            self.scene.step(dt_float);
-
-            break
-        }
         println!("{:?}", dt.subsec_nanos() as f32 / 1_000_000f32);
         Ok(())
     }
 
+    fn mouse_button_down_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        match button {
+            MouseButton::Left if self.mouse_joint.is_none() => {
+                let point = Vec2::new(x as Real, y as Real);
+                if let Some(index) = self.scene.query_point(point) {
+                    let max_force = 10.0 * scene::GRAVITY[1] * self.scene.bodies[index].mass;
+                    let joint = MouseJoint::new((index, &self.scene.bodies[index]), point, max_force);
+                    self.mouse_joint = Some(self.scene.add_joint(Joint::Mouse(joint)));
+                }
+            }
+            _ => ()
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, button: MouseButton, _x: i32, _y: i32) {
+        match button {
+            MouseButton::Left => {
+                if let Some(handle) = self.mouse_joint.take() {
+                    self.scene.remove_joint(handle);
+                }
+            }
+            _ => ()
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _state: MouseState, x: i32, y: i32, _xrel: i32, _yrel: i32) {
+        if let Some(handle) = self.mouse_joint {
+            if let Some(&mut Joint::Mouse(ref mut joint)) = self.scene.joints.get_mut(handle) {
+                joint.target = Vec2::new(x as Real, y as Real);
+            }
+        }
+    }
+
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
         graphics::clear(ctx);
 
-        for body in &self.scene.bodies {
-            match &body.shape {
-                &Shape::Circle { radius } => {
-                    if body.inv_mass == 0.0 {
-                        graphics::set_color(ctx, graphics::WHITE)?;
-                    } else {
-                        match radius {
-                            10.0 => graphics::set_color(ctx, graphics::Color::new(200./255.,101./255.,230./255.,200./255.))?,
-                            30.0 => graphics::set_color(ctx, graphics::Color::new(255./255.,0./255.,0./255.,150./255.))?,
-                            _ => graphics::set_color(ctx, graphics::Color::new(100./255.,101./255.,230./255.,200./255.))?,
-                        }
-                        //graphics::circle(ctx, DrawMode::Fill, Point { x: body.position.x, y: body.position.y }, radius, 6)?;
-                    }
-                    graphics::circle(ctx, DrawMode::Fill, Point { x: body.position.x, y: body.position.y }, radius, 32)?;
-                }
-                &Shape::Polygon { ref vertices, .. } => {
-                    let a = -body.orient.0;
-                    let rotation_matrix = Mat2::new(a.cos(), -a.sin(), a.sin(), a.cos());
-
-                    graphics::polygon(ctx, DrawMode::Fill, 
-                        vertices.iter()
-                        .map(|v| v.position)
-                        .map(|v| rotation_matrix * v)
-                        .map(|v| v + body.position)
-                        .map(|v| Point { x: v.x, y: v.y })
-                        .collect::<Vec<_>>().as_ref())?;
-                }
+        for (index, body) in self.scene.bodies.iter().enumerate() {
+            let transform = self.scene.interpolated_transform(index, self.alpha);
+            // Shapes are drawn around their origin instead of the center of mass
+            let transform = Transform {
+                position: transform.world_point(-body.local_center),
+                orient: transform.orient,
+            };
+
+            draw_shape(ctx, &body.shape, &transform, body.body_type == BodyType::Static)?;
+        }
+
+        if let Some(handle) = self.mouse_joint {
+            if let Some(&Joint::Mouse(ref joint)) = self.scene.joints.get(handle) {
+                let anchor = self.scene.interpolated_transform(joint.body, self.alpha).world_point(joint.local_anchor);
+
+                graphics::set_color(ctx, graphics::BLACK)?;
+                graphics::line(ctx, &[
+                    Point { x: anchor.x, y: anchor.y },
+                    Point { x: joint.target.x, y: joint.target.y },
+                ])?;
             }
         }
         graphics::present(ctx);
@@ -175,7 +278,14 @@ pub fn main() {
 
     //state.scene.add(create_rect(200.0, 20.0, 450.0, 400.0, Deg(80.0), false));
 
-    state.scene.add(create_rect(300.0, 20.0, 300.0, 300.0, Deg(10.0), false));
+    let mut ledge = Body::new(Shape::rounded_rect(Vec2::new(290.0, 10.0), 10.0), Vec2::new(300.0, 300.0));
+    ledge.set_orient(Deg(10.0));
+    ledge.set_static();
+    state.scene.add(ledge);
+
+    let mut peg = Body::new(Shape::regular_polygon(6, 30.0), Vec2::new(650.0, 430.0));
+    peg.set_static();
+    state.scene.add(peg);
     //state.scene.add(create_rect(150.0, 20.0, 650.0, 500.0, Deg(-20.0), false));
 
     state.scene.add(create_rect(20.0, 400.0, 1700.0, 600.0, Deg(0.0), false));
@@ -183,6 +293,33 @@ pub fn main() {
 
     state.scene.add(create_rect(400.0, 20.0, 1100.0, 600.0, Deg(-10.0), false));
 
+    let mut terrain = Body::new(Shape::Chain {
+        vertices: (0..25).map(|i| Vec2::new(80.0 * i as f32, 1040.0 - 20.0 * (i as f32 * 0.7).sin())).collect(),
+        looped: false,
+    }, Vec2::new(0.0, 0.0));
+    terrain.set_static();
+    state.scene.add(terrain);
+
+    for i in 0..3 {
+        state.scene.add(create_capsule(25.0, 15.0, 950.0 + 100.0 * i as f32, 450.0, Deg(30.0 * i as f32), true));
+    }
+
+    // A concave cup, split into convex polygons
+    let mut cup = Body::new(Shape::concave(&[
+        Vec2::new(-150.0, -80.0), Vec2::new(-130.0, -80.0), Vec2::new(-110.0, 20.0), Vec2::new(110.0, 20.0),
+        Vec2::new(130.0, -80.0), Vec2::new(150.0, -80.0), Vec2::new(130.0, 40.0), Vec2::new(-130.0, 40.0),
+    ]), Vec2::new(1400.0, 850.0));
+    cup.set_static();
+    state.scene.add(cup);
+
+    // A dumbbell out of two heavy circles on a capsule bar
+    let dumbbell = Shape::Compound { fixtures: vec![
+        Fixture { density: 2.0, ..Fixture::new(Shape::Circle { radius: 25.0 }, Vec2::new(-60.0, 0.0), Rad(0.0)) },
+        Fixture::new(Shape::Capsule { half_height: 50.0, radius: 8.0 }, Vec2::new(0.0, 0.0), Deg(90.0)),
+        Fixture { density: 2.0, ..Fixture::new(Shape::Circle { radius: 25.0 }, Vec2::new(60.0, 0.0), Rad(0.0)) },
+    ]};
+    state.scene.add(Body::new(dumbbell, Vec2::new(1400.0, 700.0)));
+
 
     if let Err(e) = event::run(ctx, state) {
         println!("Error encountered: {}", e);
@@ -0,0 +1,275 @@
+use super::types::{Vec2, Real};
+use super::body::Body;
+use super::force::{ForceGenerator, ForceFilter};
+use cgmath::InnerSpace;
+use rayon::prelude::*;
+
+// Stops subdividing when bodies sit on top of each other
+const MAX_DEPTH: usize = 32;
+
+struct Node {
+    min: Vec2,
+    size: Real,
+    mass: Real,
+    center_of_mass: Vec2,
+    // The (index, position, mass) of the body in a leaf, None for empty and internal nodes
+    body: Option<(usize, Vec2, Real)>,
+    children: Option<[usize; 4]>,
+}
+
+impl Node {
+    fn new(min: Vec2, size: Real) -> Self {
+        Node {
+            min: min,
+            size: size,
+            mass: 0.0,
+            center_of_mass: Vec2::new(0.0, 0.0),
+            body: None,
+            children: None,
+        }
+    }
+
+    fn quadrant(&self, point: Vec2) -> usize {
+        let half = self.size / 2.0;
+        let right = point.x >= self.min.x + half;
+        let bottom = point.y >= self.min.y + half;
+        (right as usize) + 2 * (bottom as usize)
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.x <= self.min.x + self.size &&
+        point.y >= self.min.y && point.y <= self.min.y + self.size
+    }
+}
+
+// Barnes-Hut quadtree holding the mass distribution of the bodies
+struct QuadTree {
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    fn new(bodies: &[(usize, Vec2, Real)]) -> Self {
+        let mut min = Vec2::new(::std::f32::INFINITY, ::std::f32::INFINITY);
+        let mut max = Vec2::new(::std::f32::NEG_INFINITY, ::std::f32::NEG_INFINITY);
+        for &(_, position, _) in bodies {
+            min.x = min.x.min(position.x);
+            min.y = min.y.min(position.y);
+            max.x = max.x.max(position.x);
+            max.y = max.y.max(position.y);
+        }
+        let size = (max.x - min.x).max(max.y - min.y).max(1.0);
+
+        let mut tree = QuadTree { nodes: vec![Node::new(min, size)] };
+        for &body in bodies {
+            tree.insert(0, body, 0);
+        }
+        tree
+    }
+
+    fn insert(&mut self, node: usize, body: (usize, Vec2, Real), depth: usize) {
+        let (_, position, mass) = body;
+
+        // Every node on the way down gets the body added to its mass
+        {
+            let node = &mut self.nodes[node];
+            let total = node.mass + mass;
+            if total > 0.0 {
+                node.center_of_mass = (node.center_of_mass * node.mass + position * mass) / total;
+            }
+            node.mass = total;
+        }
+
+        if let Some(children) = self.nodes[node].children {
+            let quadrant = self.nodes[node].quadrant(position);
+            return self.insert(children[quadrant], body, depth + 1)
+        }
+
+        let other = match self.nodes[node].body {
+            None => {
+                self.nodes[node].body = Some(body);
+                return
+            }
+            // Coincident bodies are kept together as one point mass
+            Some(_) if depth >= MAX_DEPTH => return,
+            Some(other) => other,
+        };
+
+        // Split the leaf and push both bodies down
+        let (min, half) = (self.nodes[node].min, self.nodes[node].size / 2.0);
+        let mut children = [0; 4];
+        for (i, child) in children.iter_mut().enumerate() {
+            let offset = Vec2::new((i % 2) as Real * half, (i / 2) as Real * half);
+            self.nodes.push(Node::new(min + offset, half));
+            *child = self.nodes.len() - 1;
+        }
+        self.nodes[node].children = Some(children);
+        self.nodes[node].body = None;
+
+        for &body in &[other, body] {
+            let quadrant = self.nodes[node].quadrant(body.1);
+            self.insert(children[quadrant], body, depth + 1);
+        }
+    }
+
+    // Acceleration at position, ignoring the leaf holding index
+    fn acceleration(&self, index: usize, position: Vec2, gravity: &NBodyGravity) -> Vec2 {
+        let mut acceleration = Vec2::new(0.0, 0.0);
+        let mut stack = vec![0];
+        let softening = gravity.softening * gravity.softening;
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if node.mass <= 0.0 || node.body.map(|body| body.0) == Some(index) {
+                continue
+            }
+
+            let d = node.center_of_mass - position;
+            let distance_sqr = d.magnitude2();
+
+            // A node around the body is always opened, its center of mass can be arbitrarily
+            // close to the body whatever theta is
+            let open = node.contains(position) ||
+                       node.size * node.size >= gravity.theta * gravity.theta * distance_sqr;
+
+            match node.children {
+                Some(children) if open => {
+                    stack.extend_from_slice(&children);
+                }
+                _ => {
+                    let r2 = distance_sqr + softening;
+                    if r2 > 0.0 {
+                        acceleration += d * (gravity.gravitational_constant * node.mass / (r2 * r2.sqrt()));
+                    }
+                }
+            }
+        }
+
+        acceleration
+    }
+}
+
+// Bodies attract each other with G * m_a * m_b / (r² + softening²), approximated with a
+// Barnes-Hut quadtree. Lower theta is more accurate, 0 sums over every pair.
+#[derive(Clone, Debug)]
+pub struct NBodyGravity {
+    pub gravitational_constant: Real,
+    pub softening: Real,
+    pub theta: Real,
+    pub filter: ForceFilter,
+}
+
+impl NBodyGravity {
+    pub fn new(gravitational_constant: Real, softening: Real) -> Self {
+        NBodyGravity {
+            gravitational_constant: gravitational_constant,
+            softening: softening,
+            theta: 0.5,
+            filter: ForceFilter::everywhere(),
+        }
+    }
+}
+
+impl ForceGenerator for NBodyGravity {
+    fn apply(&mut self, bodies: &mut [Body], _: Real) {
+        let sources = bodies.iter().enumerate()
+            .filter(|&(index, body)| self.filter.accepts(index, body) && body.mass > 0.0)
+            .map(|(index, body)| (index, body.position, body.mass))
+            .collect::<Vec<_>>();
+        if sources.len() < 2 {
+            return
+        }
+
+        let tree = QuadTree::new(&sources);
+        let gravity = &*self;
+        let forces = sources.par_iter()
+            .map(|&(index, position, mass)| (index, tree.acceleration(index, position, gravity) * mass))
+            .collect::<Vec<_>>();
+
+        for (index, force) in forces {
+            bodies[index].apply_force(force);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::body::Shape;
+
+    // Small linear congruential generator, enough for a reproducible cloud of bodies
+    fn cloud(count: usize, mut seed: u32) -> Vec<Body> {
+        let mut next = || {
+            seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+            (seed >> 8) as Real / (1 << 24) as Real
+        };
+
+        (0..count).map(|_| {
+            let position = Vec2::new(next() * 1000.0, next() * 1000.0);
+            Body::with_density(Shape::Circle { radius: 1.0 + next() * 4.0 }, position, 1.0)
+        }).collect()
+    }
+
+    fn direct_sum(bodies: &[Body], gravity: &NBodyGravity) -> Vec<Vec2> {
+        let softening = gravity.softening * gravity.softening;
+        bodies.iter().enumerate().map(|(i, a)| {
+            bodies.iter().enumerate().filter(|&(j, _)| j != i).fold(Vec2::new(0.0, 0.0), |force, (_, b)| {
+                let d = b.position - a.position;
+                let r2 = d.magnitude2() + softening;
+                force + d * (gravity.gravitational_constant * a.mass * b.mass / (r2 * r2.sqrt()))
+            })
+        }).collect()
+    }
+
+    #[test]
+    fn barnes_hut_matches_direct_sum() {
+        let mut bodies = cloud(200, 7);
+        let mut gravity = NBodyGravity::new(1.0, 1.0);
+        let expected = direct_sum(&bodies, &gravity);
+        gravity.apply(&mut bodies, 0.0);
+
+        let error = bodies.iter().zip(&expected).map(|(body, &f)| (body.force - f).magnitude2()).sum::<Real>().sqrt();
+        let total = expected.iter().map(|f| f.magnitude2()).sum::<Real>().sqrt();
+        assert!(error < 0.02 * total, "relative error {}", error / total);
+    }
+
+    #[test]
+    fn theta_zero_is_exact() {
+        let mut bodies = cloud(50, 11);
+        let mut gravity = NBodyGravity::new(1.0, 1.0);
+        gravity.theta = 0.0;
+        let expected = direct_sum(&bodies, &gravity);
+        gravity.apply(&mut bodies, 0.0);
+
+        for (body, &f) in bodies.iter().zip(&expected) {
+            assert!((body.force - f).magnitude() <= 1e-4 * f.magnitude(), "{:?} != {:?}", body.force, f);
+        }
+    }
+
+    #[test]
+    fn coincident_bodies() {
+        let mut bodies = (0..8).map(|i| {
+            // Four bodies on the same spot and four a hair apart
+            let offset = if i < 4 { 0.0 } else { i as Real * 1e-6 };
+            Body::new(Shape::Circle { radius: 1.0 }, Vec2::new(100.0 + offset, 100.0))
+        }).collect::<Vec<_>>();
+        bodies.push(Body::new(Shape::Circle { radius: 1.0 }, Vec2::new(500.0, 300.0)));
+
+        let sources = bodies.iter().enumerate().map(|(i, body)| (i, body.position, body.mass)).collect::<Vec<_>>();
+        let tree = QuadTree::new(&sources);
+        fn depth(tree: &QuadTree, node: usize) -> usize {
+            tree.nodes[node].children.map_or(0, |children| {
+                1 + children.iter().map(|&child| depth(tree, child)).max().unwrap()
+            })
+        }
+        assert!(depth(&tree, 0) <= MAX_DEPTH);
+        assert_eq!(tree.nodes[0].mass, bodies.iter().map(|body| body.mass).sum::<Real>());
+
+        for &softening in &[0.0, 1.0] {
+            let mut bodies = bodies.clone();
+            NBodyGravity::new(1.0, softening).apply(&mut bodies, 0.0);
+            for body in &bodies {
+                assert!(body.force.x.is_finite() && body.force.y.is_finite(), "{:?}", body.force);
+            }
+        }
+    }
+}
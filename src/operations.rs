@@ -24,4 +24,248 @@ pub fn dist_sqr(a: Vec2, b: Vec2) -> Real {
 // Equal
 pub fn float_cmp(a: Real, b: Real) -> bool {
     (a - b).abs() <= EPSILON
-}
\ No newline at end of file
+}
+
+// Closest point to p on the segment a -> b
+pub fn closest_point_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let e = b - a;
+    let ee = dot(e, e);
+    if ee < EPSILON {
+        return a
+    }
+    a + e * (dot(p - a, e) / ee).max(0.0).min(1.0)
+}
+
+// Area and centroid of a polygon, b2PolygonShape::ComputeMass without the inertia
+pub fn polygon_area_centroid(points: &[Vec2]) -> (Real, Vec2) {
+    let mut area = 0.0;
+    let mut c = Vec2::new(0.0, 0.0);
+    if points.len() < 3 {
+        return (area, c)
+    }
+
+    let origin = points[0];
+    for i in 1..points.len() - 1 {
+        let e1 = points[i] - origin;
+        let e2 = points[i + 1] - origin;
+        let triangle_area = 0.5 * cross_vectors(e1, e2);
+
+        area += triangle_area;
+        c += (e1 + e2) * (triangle_area / 3.0);
+    }
+
+    if area.abs() > EPSILON {
+        c /= area;
+    }
+    (area, origin + c)
+}
+
+// Sutherland-Hodgman, keeps the part of a polygon inside a convex counter clockwise polygon
+pub fn clip_polygon(points: &[Vec2], clip: &[Vec2]) -> Vec<Vec2> {
+    let mut output = points.to_vec();
+
+    for i in 0..clip.len() {
+        let a = clip[i];
+        let b = clip[(i + 1) % clip.len()];
+        let input = output;
+        output = Vec::with_capacity(input.len() + 1);
+
+        for j in 0..input.len() {
+            let p = input[j];
+            let q = input[(j + 1) % input.len()];
+            let side_p = cross_vectors(b - a, p - a);
+            let side_q = cross_vectors(b - a, q - a);
+
+            if side_p >= 0.0 {
+                output.push(p);
+            }
+            if (side_p >= 0.0) != (side_q >= 0.0) {
+                output.push(p + (q - p) * (side_p / (side_p - side_q)));
+            }
+        }
+    }
+
+    output
+}
+
+// Splits a simple polygon, in either winding, into convex counter clockwise pieces.
+// The polygon is ear clipped into triangles, which are then merged back together
+// for as long as the result stays convex (Hertel-Mehlhorn). Panics on outlines that
+// cross themselves.
+pub fn convex_decomposition(points: &[Vec2]) -> Vec<Vec<Vec2>> {
+    let mut polygon = remove_collinear(points);
+    assert!(polygon.len() >= 3);
+    assert!(is_simple(&polygon), "convex_decomposition: the outline crosses itself");
+    if polygon_area_centroid(&polygon).0 < 0.0 {
+        polygon.reverse();
+    }
+
+    let mut pieces = Vec::new();
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let ear = (0..n).find(|&i| is_ear(&polygon, (i + n - 1) % n, i, (i + 1) % n));
+
+        match ear {
+            Some(i) => {
+                pieces.push(vec![polygon[(i + n - 1) % n], polygon[i], polygon[(i + 1) % n]]);
+                polygon.remove(i);
+                polygon = remove_collinear(&polygon);
+            }
+            // A simple polygon always has an ear, this is left for degenerate outlines
+            None => panic!("convex_decomposition: no ear found in {:?}", polygon)
+        }
+    }
+    if polygon.len() == 3 {
+        pieces.push(polygon);
+    }
+
+    // Drop the diagonals that aren't needed to keep the pieces convex
+    'merge: loop {
+        for i in 0..pieces.len() {
+            for j in i + 1..pieces.len() {
+                if let Some(merged) = merge_convex(&pieces[i], &pieces[j]) {
+                    pieces[i] = merged;
+                    pieces.swap_remove(j);
+                    continue 'merge
+                }
+            }
+        }
+        break
+    }
+
+    pieces
+}
+
+// Removes repeated points and points on a straight line between their neighbours
+fn remove_collinear(points: &[Vec2]) -> Vec<Vec2> {
+    let mut points = points.to_vec();
+    let mut i = 0;
+    while points.len() > 2 && i < points.len() {
+        let n = points.len();
+        let (a, b, c) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+        if dist_sqr(a, b) < EPSILON || cross_vectors(b - a, c - b).abs() < EPSILON {
+            points.remove(i);
+            i = i.saturating_sub(1);
+        } else {
+            i += 1;
+        }
+    }
+    points
+}
+
+// Whether no two edges of the outline cross each other
+fn is_simple(polygon: &[Vec2]) -> bool {
+    let n = polygon.len();
+    let crosses = |(a1, a2): (Vec2, Vec2), (b1, b2): (Vec2, Vec2)| {
+        let side = |p: Vec2, q: Vec2, r: Vec2| cross_vectors(q - p, r - p);
+        side(a1, a2, b1) * side(a1, a2, b2) < 0.0 && side(b1, b2, a1) * side(b1, b2, a2) < 0.0
+    };
+
+    (0..n).all(|i| {
+        (i + 2..n).filter(|&j| (j + 1) % n != i).all(|j| {
+            !crosses((polygon[i], polygon[(i + 1) % n]), (polygon[j], polygon[(j + 1) % n]))
+        })
+    })
+}
+
+// The corner at b is convex and the triangle a, b, c holds no other point of the polygon
+fn is_ear(polygon: &[Vec2], a: usize, b: usize, c: usize) -> bool {
+    let (pa, pb, pc) = (polygon[a], polygon[b], polygon[c]);
+    if cross_vectors(pb - pa, pc - pb) <= 0.0 {
+        return false
+    }
+
+    polygon.iter().all(|&p| {
+        p == pa || p == pb || p == pc ||
+        cross_vectors(pb - pa, p - pa) < 0.0 ||
+        cross_vectors(pc - pb, p - pb) < 0.0 ||
+        cross_vectors(pa - pc, p - pc) < 0.0
+    })
+}
+
+// Joins two counter clockwise polygons along a shared edge if the result is convex
+fn merge_convex(a: &[Vec2], b: &[Vec2]) -> Option<Vec<Vec2>> {
+    for i in 0..a.len() {
+        let (a1, a2) = (a[i], a[(i + 1) % a.len()]);
+        let j = match (0..b.len()).find(|&j| b[j] == a2 && b[(j + 1) % b.len()] == a1) {
+            Some(j) => j,
+            None => continue,
+        };
+
+        // Walk a from a2 around to a1, then b from after a1 around to before a2
+        let mut merged = (0..a.len()).map(|k| a[(i + 1 + k) % a.len()]).collect::<Vec<_>>();
+        merged.extend((2..b.len()).map(|k| b[(j + k) % b.len()]));
+        let merged = remove_collinear(&merged);
+
+        let n = merged.len();
+        let convex = (0..n).all(|k| {
+            cross_vectors(merged[(k + 1) % n] - merged[k], merged[(k + 2) % n] - merged[(k + 1) % n]) > 0.0
+        });
+        return if convex { Some(merged) } else { None }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::body::Shape;
+
+    fn assert_decomposition(outline: &[Vec2]) {
+        let pieces = convex_decomposition(outline);
+
+        // Shape::polygon asserts the pieces are strictly convex and counter clockwise
+        for piece in &pieces {
+            Shape::polygon(piece);
+        }
+
+        let area = pieces.iter().map(|piece| polygon_area_centroid(piece).0).sum::<Real>();
+        let outline_area = polygon_area_centroid(outline).0.abs();
+        assert!((area - outline_area).abs() < 1e-3 * outline_area, "{} != {}", area, outline_area);
+    }
+
+    fn both_windings(outline: &[Vec2]) {
+        assert_decomposition(outline);
+        let reversed = outline.iter().rev().cloned().collect::<Vec<_>>();
+        assert_decomposition(&reversed);
+    }
+
+    #[test]
+    fn convex_outline() {
+        both_windings(&[Vec2::new(0.0, 0.0), Vec2::new(40.0, 0.0), Vec2::new(50.0, 30.0), Vec2::new(10.0, 40.0)]);
+    }
+
+    #[test]
+    fn l_shape() {
+        both_windings(&[
+            Vec2::new(0.0, 0.0), Vec2::new(40.0, 0.0), Vec2::new(40.0, 40.0),
+            Vec2::new(30.0, 40.0), Vec2::new(30.0, 10.0), Vec2::new(0.0, 10.0),
+        ]);
+    }
+
+    #[test]
+    fn star() {
+        let outline = (0..10).map(|i| {
+            let angle = i as Real * ::std::f32::consts::PI / 5.0;
+            let radius = if i % 2 == 0 { 50.0 } else { 20.0 };
+            Vec2::new(angle.cos(), angle.sin()) * radius
+        }).collect::<Vec<_>>();
+        both_windings(&outline);
+    }
+
+    #[test]
+    fn comb_with_collinear_points() {
+        both_windings(&[
+            Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(20.0, 0.0), Vec2::new(30.0, 0.0),
+            Vec2::new(30.0, 30.0), Vec2::new(25.0, 30.0), Vec2::new(25.0, 10.0), Vec2::new(20.0, 10.0),
+            Vec2::new(20.0, 30.0), Vec2::new(10.0, 30.0), Vec2::new(10.0, 10.0), Vec2::new(5.0, 10.0),
+            Vec2::new(5.0, 30.0), Vec2::new(0.0, 30.0),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "crosses itself")]
+    fn crossing_outline() {
+        convex_decomposition(&[Vec2::new(0.0, 0.0), Vec2::new(30.0, 20.0), Vec2::new(30.0, 0.0), Vec2::new(0.0, 20.0)]);
+    }
+}
@@ -1,18 +1,95 @@
 use super::types::{Real, Vec2};
 use super::{Body, Shape};
+use super::body::{MassData, Transform};
 use super::collision::{self, Manifold, ManifoldData};
+use super::joint::{Joint, JointHandle, JointSet};
+use super::integrator::Integrator;
+use super::force::ForceGenerator;
 use super::operations::{cross_vectors, cross_real_vector, float_cmp};
-use cgmath::{dot, InnerSpace};
+use cgmath::{dot, InnerSpace, Matrix, Rad};
 use rayon::prelude::*;
 
 pub static GRAVITY : [f32; 2] = [0.0, 500.0];
 pub static EPSILON : f32 = 0.0001;
 pub static FRAME_TIME: f32 = 1.0/60.0;
 
+pub enum Event {
+    // The joint exceeded its break force or torque and was removed from the scene
+    JointBroken {
+        handle: JointHandle,
+        joint: Joint,
+        force: Vec2,
+        torque: Real,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub enum PositionCorrection {
+    // Pushes bodies apart along the contact normal once per step, using the
+    // penetration found by the collision pass (Manifold::PositionalCorrect)
+    Linear,
+    // Iterates on the penetration recomputed from the current positions,
+    // moving and rotating bodies without adding velocity
+    NonLinearGaussSeidel,
+}
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
+pub struct SolverConfig {
+    pub velocity_iterations: u32,
+    // Iterations of the joint position solver
+    pub position_iterations: u32,
+    // Penetration allowed before positional correction kicks in
+    pub slop: Real,
+    // Fraction of the penetration corrected each step
+    pub baumgarte: Real,
+    // Contacts with a relative speed below this don't bounce
+    pub restitution_threshold: Real,
+    // Largest positional correction applied to a contact in one step, no limit
+    // when None
+    pub max_correction: Option<Real>,
+    pub position_correction: PositionCorrection,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        SolverConfig {
+            velocity_iterations: 10,
+            position_iterations: 3,
+            slop: 0.05,
+            baumgarte: 0.4,
+            restitution_threshold: Vec2::new(GRAVITY[0], GRAVITY[1]).magnitude() * FRAME_TIME,
+            max_correction: None,
+            position_correction: PositionCorrection::Linear,
+        }
+    }
+}
+
+// With the serialize feature a scene can be saved and loaded as a whole, except for
+// its force generators
+#[cfg_attr(feature = "serialize", derive(Serialize, Deserialize))]
 pub struct Scene {
     delta: Real,
-    iterations: u32,
+    pub solver: SolverConfig,
     pub bodies: Vec<Body>,
+    pub joints: JointSet,
+    pub integrator: Integrator,
+    // Not saved, generators are dropped on save and have to be added again after
+    // loading a scene
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    pub force_generators: Vec<Box<dyn ForceGenerator>>,
+    // Fixed step used by update
+    pub time_step: Real,
+    // Most fixed steps a single update may run, the rest of the time is dropped
+    pub max_steps: u32,
+    // Each fixed step is split in this many solver steps
+    pub substeps: u32,
+    accumulator: Real,
+    // Body transforms from before the last step, for render interpolation
+    previous_transforms: Vec<Transform>,
+    #[cfg_attr(feature = "serialize", serde(skip))]
+    events: Vec<Event>,
 }
 
 pub type BodyIndex = usize;
@@ -21,47 +98,314 @@ impl Scene {
     pub fn new() -> Self {
         Scene {
             delta: 0.0,
-            iterations: 10,
+            solver: SolverConfig::default(),
             bodies: vec![],
+            joints: JointSet::new(),
+            integrator: Integrator::ImpulseEngine,
+            force_generators: vec![],
+            time_step: FRAME_TIME,
+            max_steps: 5,
+            substeps: 1,
+            accumulator: 0.0,
+            previous_transforms: vec![],
+            events: vec![],
         }
     }
 
     // Scene::Add
-    pub fn add(&mut self, body: Body) {
+    pub fn add(&mut self, body: Body) -> BodyIndex {
+        self.previous_transforms.push(body.transform());
         self.bodies.push(body);
+        self.bodies.len() - 1
+    }
+
+    pub fn add_joint(&mut self, joint: Joint) -> JointHandle {
+        self.joints.insert(joint)
+    }
+
+    pub fn add_force_generator<G: ForceGenerator + 'static>(&mut self, generator: G) -> usize {
+        self.force_generators.push(Box::new(generator));
+        self.force_generators.len() - 1
+    }
+
+    // Swaps the shape of a body and recomputes its mass. Joints stay attached to the
+    // same points in the shape's space.
+    pub fn set_shape(&mut self, index: BodyIndex, shape: Shape) {
+        let old_center = self.bodies[index].local_center;
+        self.bodies[index].set_shape(shape);
+        self.center_moved(index, old_center);
+    }
+
+    // Scales the shape of a body around its origin and recomputes its mass
+    pub fn scale_body(&mut self, index: BodyIndex, factor: Real) {
+        let old_center = self.bodies[index].local_center;
+        self.bodies[index].scale(factor);
+        self.center_moved(index, old_center);
+    }
+
+    // Body::set_mass_data that keeps the joints on the body in place
+    pub fn set_mass_data(&mut self, index: BodyIndex, mass_data: MassData) {
+        let old_center = self.bodies[index].local_center;
+        self.bodies[index].set_mass_data(mass_data);
+        self.center_moved(index, old_center);
+    }
+
+    // Moves what's stored relative to the center of mass of a body along with it
+    fn center_moved(&mut self, index: BodyIndex, old_center: Vec2) {
+        let shift = old_center - self.bodies[index].local_center;
+        for joint in self.joints.joints_mut() {
+            joint.shift_anchors(index, shift);
+        }
+
+        if let Some(previous) = self.previous_transforms.get_mut(index) {
+            previous.position -= previous.rotation() * shift;
+        }
+    }
+
+    // Removes a joint, None if it was already removed or broke
+    pub fn remove_joint(&mut self, handle: JointHandle) -> Option<Joint> {
+        self.joints.remove(handle)
+    }
+
+    // Returns the events generated since the last call
+    pub fn take_events(&mut self) -> Vec<Event> {
+        ::std::mem::replace(&mut self.events, vec![])
+    }
+
+    // Returns the force and torque the joint applied during the last step
+    pub fn joint_reaction(&self, handle: JointHandle) -> Option<(Vec2, Real)> {
+        let inv_delta = if self.delta > 0.0 { 1.0 / self.delta } else { 0.0 };
+        self.joints.get(handle).map(|joint| (joint.reaction_force(inv_delta), joint.reaction_torque(inv_delta)))
+    }
+
+    // Returns the first dynamic body that contains the point
+    pub fn query_point(&self, point: Vec2) -> Option<BodyIndex> {
+        self.bodies.iter().position(|body| body.is_dynamic() && body.contains_point(point))
+    }
+
+    // b2World::RayCast, returns the first body hit along p1 -> p2 with the fraction and normal
+    pub fn ray_cast(&self, p1: Vec2, p2: Vec2) -> Option<(BodyIndex, Real, Vec2)> {
+        let mut closest: Option<(BodyIndex, Real, Vec2)> = None;
+        for (index, body) in self.bodies.iter().enumerate() {
+            if let Some((fraction, normal)) = body.ray_cast(p1, p2) {
+                if closest.map_or(true, |(_, closest, _)| fraction < closest) {
+                    closest = Some((index, fraction, normal));
+                }
+            }
+        }
+        closest
+    }
+
+    // Pushes the dynamic bodies within radius of center away from it, a negative impulse
+    // pulls them in. The impulse is per unit of width the body shows to the blast, scaled
+    // by (1 - distance / radius)^falloff, and applied at the closest point of the body.
+    pub fn explode(&mut self, center: Vec2, radius: Real, impulse: Real, falloff: Real) {
+        self.blast(center, radius, impulse, falloff, false)
+    }
+
+    // Same as explode, but bodies hidden behind other bodies are left alone
+    pub fn explode_occluded(&mut self, center: Vec2, radius: Real, impulse: Real, falloff: Real) {
+        self.blast(center, radius, impulse, falloff, true)
+    }
+
+    fn blast(&mut self, center: Vec2, radius: Real, impulse: Real, falloff: Real, occlusion: bool) {
+        for i in 0..self.bodies.len() {
+            if !self.bodies[i].is_dynamic() {
+                continue
+            }
+
+            let point = self.bodies[i].closest_point(center);
+            let distance = (point - center).magnitude();
+            if distance > radius {
+                continue
+            }
+
+            // Bodies over the center are pushed away from their centroid
+            let direction = if distance > EPSILON {
+                (point - center) / distance
+            } else {
+                let d = self.bodies[i].position - center;
+                if d.magnitude2() < EPSILON * EPSILON {
+                    continue
+                }
+                d.normalize()
+            };
+
+            if occlusion {
+                if let Some((hit, _, _)) = self.ray_cast(center, point) {
+                    if hit != i {
+                        continue
+                    }
+                }
+            }
+
+            let body = &mut self.bodies[i];
+            let scale = (1.0 - distance / radius).max(0.0).powf(falloff) * body.projected_width(direction);
+            body.apply_impulse_at_point(direction * (impulse * scale), point);
+        }
+    }
+
+    // Advances the scene by frame_delta in fixed steps. Returns how far the time left
+    // in the accumulator is into the next step, to interpolate rendering with.
+    pub fn update(&mut self, frame_delta: Real) -> Real {
+        self.accumulator += frame_delta;
+
+        let mut steps = 0;
+        while self.accumulator >= self.time_step && steps < self.max_steps {
+            self.accumulator -= self.time_step;
+            steps += 1;
+
+            self.save_transforms();
+
+            let substeps = self.substeps.max(1);
+            let delta = self.time_step / substeps as Real;
+            for _ in 0..substeps {
+                self.solve(delta);
+            }
+            self.clear_forces();
+        }
+
+        // Avoid the spiral of death when the simulation can't keep up
+        if self.accumulator >= self.time_step {
+            self.accumulator %= self.time_step;
+        }
+
+        self.accumulator / self.time_step
     }
 
     // Scene::Step
     pub fn step(&mut self, delta: Real) {
-        let contact_data = self.generate_contact_list();
+        self.save_transforms();
+        self.solve(delta);
+        self.clear_forces();
+    }
+
+    // Transform of the body between the last two steps, alpha being the value returned by update
+    pub fn interpolated_transform(&self, index: BodyIndex, alpha: Real) -> Transform {
+        let current = self.bodies[index].transform();
+        match self.previous_transforms.get(index) {
+            Some(previous) => previous.lerp(&current, alpha),
+            None => current,
+        }
+    }
+
+    fn save_transforms(&mut self) {
+        self.previous_transforms.clear();
+        self.previous_transforms.extend(self.bodies.iter().map(|body| body.transform()));
+    }
 
+    fn clear_forces(&mut self) {
         for body in &mut self.bodies {
-            body.integrate_forces(delta);
+            body.force = Vec2::new(0.0, 0.0);
+            body.torque = 0.0;
+        }
+    }
+
+    fn solve(&mut self, delta: Real) {
+        self.delta = delta;
+        let contact_data = self.generate_contact_list();
+        let constrained = self.constrained_bodies(&contact_data);
+
+        // Generated forces only last for this step, keep the ones applied by the user
+        let applied_forces = self.bodies.iter().map(|body| (body.force, body.torque)).collect::<Vec<_>>();
+        for generator in &mut self.force_generators {
+            generator.apply(&mut self.bodies, delta);
+        }
+
+        for (body, &constrained) in self.bodies.iter_mut().zip(&constrained) {
+            self.integrator.integrate_forces(body, delta, constrained);
         }
 
         let mut contacts = Vec::new();
         for data in &contact_data {
-            let contact = data.initialize(delta, &self.bodies[data.pair.0], &self.bodies[data.pair.1]);
+            let contact = data.initialize(self.solver.restitution_threshold, &self.bodies[data.pair.0], &self.bodies[data.pair.1]);
             contacts.push(contact);
         }
 
-        for _ in 0..self.iterations {
+        for joint in self.joints.joints_mut() {
+            joint.init_velocity(&mut self.bodies, delta);
+        }
+
+        for _ in 0..self.solver.velocity_iterations {
+            for joint in self.joints.joints_mut() {
+                joint.solve_velocity(&mut self.bodies);
+            }
             for contact in &contacts {
                 self.apply_impulse(&contact);
             }
         }
 
-        for body in &mut self.bodies {
-            body.integrate_velocity(delta);
+        let integrator = self.integrator;
+        let generators = &mut self.force_generators;
+        integrator.integrate_velocities(&mut self.bodies, &constrained, delta, |bodies| {
+            for (body, &(force, torque)) in bodies.iter_mut().zip(&applied_forces) {
+                body.force = force;
+                body.torque = torque;
+            }
+            for generator in generators.iter_mut() {
+                generator.apply(bodies, delta);
+            }
+        });
+
+        if self.solver.position_correction == PositionCorrection::Linear {
+            for contact in &mut contacts {
+                self.positional_correct(&contact);
+            }
+        }
+
+        for _ in 0..self.solver.position_iterations {
+            let mut solved = match self.solver.position_correction {
+                PositionCorrection::Linear => true,
+                PositionCorrection::NonLinearGaussSeidel => self.solve_contact_positions(&contacts),
+            };
+            for joint in self.joints.joints_mut() {
+                solved = joint.solve_position(&mut self.bodies) && solved;
+            }
+            if solved {
+                break
+            }
         }
 
-        for contact in &mut contacts {
-            self.positional_correct(&contact);
+        for (body, &(force, torque)) in self.bodies.iter_mut().zip(&applied_forces) {
+            body.force = force;
+            body.torque = torque;
         }
 
-        for body in &mut self.bodies {
-            body.force = Vec2::new(0.0, 0.0);
-            body.torque = 0.0;
+        self.break_joints();
+    }
+
+    // Bodies touched by a contact or joint this step
+    fn constrained_bodies(&self, contact_data: &[ManifoldData]) -> Vec<bool> {
+        let mut constrained = vec![false; self.bodies.len()];
+        for data in contact_data {
+            constrained[data.pair.0] = true;
+            constrained[data.pair.1] = true;
+        }
+        for (_, joint) in self.joints.iter() {
+            for index in joint.bodies() {
+                constrained[index] = true;
+            }
+        }
+        constrained
+    }
+
+    fn break_joints(&mut self) {
+        let inv_delta = if self.delta > 0.0 { 1.0 / self.delta } else { 0.0 };
+
+        let broken = self.joints.iter()
+            .filter(|&(_, joint)| joint.should_break(inv_delta))
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+
+        for handle in broken {
+            if let Some(joint) = self.joints.remove(handle) {
+                self.events.push(Event::JointBroken {
+                    handle: handle,
+                    force: joint.reaction_force(inv_delta),
+                    torque: joint.reaction_torque(inv_delta),
+                    joint: joint,
+                });
+            }
         }
     }
 
@@ -111,41 +455,28 @@ impl Scene {
 
         for (i, body_a) in self.bodies.iter().enumerate() {
             for (j, body_b) in self.bodies.iter().enumerate().skip(i + 1) {
-                if body_a.inv_mass == 0.0 && body_b.inv_mass == 0.0 {
+                if !body_a.is_dynamic() && !body_b.is_dynamic() {
                     continue
                 }
-                match (&body_a.shape, &body_b.shape) {
-                    (&Shape::Circle { radius: r1 }, &Shape::Circle { radius: r2 }) => {
-                        if let Some(manifold_data) = collision::circle_circle(
-                            (i, r1, body_a),
-                            (j, r2, body_b)
-                        ) {
-                            ret.push(manifold_data);
-                        }
-                    }
-                    (&Shape::Circle { radius }, &Shape::Polygon { ref orientation, ref vertices }) => {
-                        if let Some( manifold_data) = collision::circle_polygon(
-                            (i, radius, body_a),
-                            (j, orientation, vertices, body_b)
-                        ) {
-                            ret.push(manifold_data);
-                        }
-                    }
-                    (&Shape::Polygon { ref orientation, ref vertices }, &Shape::Circle { radius }) => {
-                        if let Some(manifold_data) = collision::circle_polygon(
-                            (j, radius, body_b),
-                            (i, orientation, vertices, body_a)
-                        ) {
-                            ret.push(manifold_data);
-                        }
-                    }
-                    _ => unimplemented!()
+                if self.joined(i, j) {
+                    continue
                 }
+                ret.extend(self.collide(i, j));
             }
         }
         ret
     }
 
+    // Narrow phase for a single pair of bodies at their current positions
+    fn collide(&self, i: BodyIndex, j: BodyIndex) -> Vec<ManifoldData> {
+        collide_bodies((i, &self.bodies[i]), (j, &self.bodies[j]))
+    }
+
+    // Bodies connected by a joint don't collide unless the joint allows it
+    fn joined(&self, i_a: BodyIndex, i_b: BodyIndex) -> bool {
+        self.joints.iter().any(|(_, joint)| !joint.collide_connected() && joint.connects(i_a, i_b))
+    }
+
     fn get_two_mut(&mut self, i_a: BodyIndex, i_b: BodyIndex) -> (&mut Body, &mut Body) {
         assert!(i_a != i_b); // Can't borrow the same value twice
         assert!(i_a < self.bodies.len());
@@ -167,11 +498,9 @@ impl Scene {
         let (i_a, i_b) = m.pair;
         let (body_a, body_b) = self.get_two_mut(i_a, i_b);
 
-        if float_cmp(body_a.inv_mass + body_b.inv_mass, 0.0) {
-            //InfiniteMassCorrection
-            body_a.velocity = Vec2::new(0.0, 0.0);
-            body_b.velocity = Vec2::new(0.0, 0.0);
-
+        // InfiniteMassCorrection, static and kinematic bodies keep their velocity and
+        // a heavy dynamic body still gets its impulse however small its inverse mass is
+        if !body_a.is_dynamic() && !body_b.is_dynamic() {
             return
         }
 
@@ -192,7 +521,7 @@ impl Scene {
                 let rb_cross_n = cross_vectors(rb, m.normal);
                 
                 let inv_mass_sum = 
-                    body_a.inv_mass + body_b.inv_mass +
+                    body_a.inv_mass_along(m.normal) + body_b.inv_mass_along(m.normal) +
                     ra_cross_n.powi(2) * body_a.inv_inertia +
                     rb_cross_n.powi(2) * body_b.inv_inertia;
                 
@@ -241,18 +570,419 @@ impl Scene {
         }
     }
 
+    // b2ContactSolver::SolvePositionConstraints, the contacts are recollided every iteration
+    // and the separation of each point follows the bodies as they are moved
+    fn solve_contact_positions(&mut self, contacts: &[Manifold]) -> bool {
+        let config = self.solver;
+        let mut min_separation: Real = 0.0;
+
+        // Pairs with several manifolds are next to each other
+        let mut pairs = contacts.iter().map(|contact| contact.pair).collect::<Vec<_>>();
+        pairs.dedup();
+
+        for (i, j) in pairs {
+            for data in self.collide(i, j) {
+                let (body_a, body_b) = self.get_two_mut(data.pair.0, data.pair.1);
+
+                // Anchor the surface points on each body so the separation can be
+                // measured again after every correction, like b2PositionSolverManifold
+                let local_normal = body_a.rotation().transpose() * data.normal;
+                let anchors = data.contacts.iter().zip(&data.penetrations).map(|(&point, &penetration)| {
+                    let offset = data.normal * (penetration * 0.5);
+                    (body_a.local_point(point + offset), body_b.local_point(point - offset))
+                }).collect::<Vec<_>>();
+
+                for (local_a, local_b) in anchors {
+                    let normal = body_a.rotation() * local_normal;
+                    let (point_a, point_b) = (body_a.world_point(local_a), body_b.world_point(local_b));
+                    let point = (point_a + point_b) * 0.5;
+                    let ra = point - body_a.position;
+                    let rb = point - body_b.position;
+
+                    let separation = dot(point_b - point_a, normal);
+                    min_separation = min_separation.min(separation);
+
+                    let c = (config.baumgarte * (separation + config.slop)).min(0.0);
+                    let c = config.max_correction.map_or(c, |limit| c.max(-limit));
+
+                    let rna = cross_vectors(ra, normal);
+                    let rnb = cross_vectors(rb, normal);
+                    let k = body_a.inv_mass_along(normal) + body_b.inv_mass_along(normal) +
+                            body_a.inv_inertia * rna * rna + body_b.inv_inertia * rnb * rnb;
+
+                    let impulse = if k > 0.0 { -c / k } else { 0.0 };
+                    let p = normal * impulse;
+
+                    body_a.position -= body_a.constrain_translation(p * body_a.inv_mass);
+                    let orient_a = body_a.orient.0 - body_a.inv_inertia * cross_vectors(ra, p);
+                    body_a.set_orient(Rad(orient_a));
+
+                    body_b.position += body_b.constrain_translation(p * body_b.inv_mass);
+                    let orient_b = body_b.orient.0 + body_b.inv_inertia * cross_vectors(rb, p);
+                    body_b.set_orient(Rad(orient_b));
+                }
+            }
+        }
+
+        // The solver can't push bodies apart completely, some overlap is left
+        min_separation >= -3.0 * config.slop
+    }
+
     // Manifold::PositionalCorrect
     fn positional_correct(&mut self, m: &Manifold) {
+        let config = self.solver;
         let (body_a, body_b) = self.get_two_mut(m.pair.0, m.pair.1);
 
-        let k_slop = 0.05;
-        let percent = 0.4;
+        let inv_mass_sum = body_a.inv_mass_along(m.normal) + body_b.inv_mass_along(m.normal);
+        if inv_mass_sum == 0.0 {
+            return
+        }
 
-        let correction = ((m.penetration - k_slop).max(0.0) / (body_a.inv_mass + body_b.inv_mass)) * m.normal * percent;
+        let depth = (m.penetration - config.slop).max(0.0) * config.baumgarte;
+        let depth = config.max_correction.map_or(depth, |limit| depth.min(limit));
+        let correction = (depth / inv_mass_sum) * m.normal;
 
-        body_a.position -= correction * body_a.inv_mass;
-        body_b.position += correction * body_b.inv_mass;
+        body_a.position -= body_a.constrain_translation(correction * body_a.inv_mass);
+        body_b.position += body_b.constrain_translation(correction * body_b.inv_mass);
     }
 }
 
+fn collide_bodies((i, body_a): (BodyIndex, &Body), (j, body_b): (BodyIndex, &Body)) -> Vec<ManifoldData> {
+    // Compound bodies collide fixture by fixture
+    match (&body_a.shape, &body_b.shape) {
+        (&Shape::Compound { .. }, _) | (_, &Shape::Compound { .. }) => {
+            return collide_fixtures((i, body_a), (j, body_b))
+        }
+        _ => ()
+    }
+
+    // Edges and chains get a manifold per segment
+    if !body_a.shape.edges().is_empty() {
+        return collide_edges((i, body_a), (j, body_b))
+    }
+    if !body_b.shape.edges().is_empty() {
+        return collide_edges((j, body_b), (i, body_a))
+    }
+
+    let manifold = match (&body_a.shape, &body_b.shape) {
+        (&Shape::Circle { radius: r1 }, &Shape::Circle { radius: r2 }) => {
+            collision::circle_circle(
+                (i, r1, body_a),
+                (j, r2, body_b)
+            )
+        }
+        (&Shape::Circle { radius }, &Shape::Polygon { ref orientation, ref vertices, radius: polygon_radius }) => {
+            collision::circle_polygon(
+                (i, radius, body_a),
+                (j, orientation, vertices, polygon_radius, body_b)
+            )
+        }
+        (&Shape::Polygon { ref orientation, ref vertices, radius: polygon_radius }, &Shape::Circle { radius }) => {
+            collision::circle_polygon(
+                (j, radius, body_b),
+                (i, orientation, vertices, polygon_radius, body_a)
+            )
+        }
+        (&Shape::Capsule { half_height, radius: r1 }, &Shape::Circle { radius: r2 }) => {
+            collision::capsule_circle(
+                (i, half_height, r1, body_a),
+                (j, r2, body_b)
+            )
+        }
+        (&Shape::Circle { radius: r1 }, &Shape::Capsule { half_height, radius: r2 }) => {
+            collision::capsule_circle(
+                (j, half_height, r2, body_b),
+                (i, r1, body_a)
+            )
+        }
+        (&Shape::Capsule { half_height: h1, radius: r1 }, &Shape::Capsule { half_height: h2, radius: r2 }) => {
+            collision::capsule_capsule(
+                (i, h1, r1, body_a),
+                (j, h2, r2, body_b)
+            )
+        }
+        (&Shape::Capsule { half_height, radius }, &Shape::Polygon { ref orientation, ref vertices, radius: polygon_radius }) => {
+            collision::capsule_polygon(
+                (i, half_height, radius, body_a),
+                (j, orientation, vertices, polygon_radius, body_b)
+            )
+        }
+        (&Shape::Polygon { ref orientation, ref vertices, radius: polygon_radius }, &Shape::Capsule { half_height, radius }) => {
+            collision::capsule_polygon(
+                (j, half_height, radius, body_b),
+                (i, orientation, vertices, polygon_radius, body_a)
+            )
+        }
+        (&Shape::Polygon { orientation: ref o1, vertices: ref v1, radius: r1 }, &Shape::Polygon { orientation: ref o2, vertices: ref v2, radius: r2 }) => {
+            collision::polygon_polygon(
+                (i, o1, v1, r1, body_a),
+                (j, o2, v2, r2, body_b)
+            )
+        }
+        // Edges, chains and compound bodies were split up above
+        _ => None
+    };
+    manifold.into_iter().collect()
+}
 
+// Collides every segment of the edge or chain on body_a with body_b
+fn collide_edges((i, body_a): (BodyIndex, &Body), (j, body_b): (BodyIndex, &Body)) -> Vec<ManifoldData> {
+    body_a.shape.edges().iter().filter_map(|edge| {
+        match body_b.shape {
+            Shape::Circle { radius } => {
+                collision::edge_circle((i, edge, body_a), (j, radius, body_b))
+            }
+            Shape::Polygon { ref vertices, radius, .. } => {
+                collision::edge_polygon((i, edge, body_a), (j, vertices, radius, body_b))
+            }
+            Shape::Capsule { half_height, radius } => {
+                collision::edge_capsule((i, edge, body_a), (j, half_height, radius, body_b))
+            }
+            // Edges don't collide with each other, compound bodies are split up by collide_fixtures
+            Shape::Edge { .. } | Shape::Chain { .. } | Shape::Compound { .. } => None,
+        }
+    }).collect()
+}
+
+// Collides the fixtures of compound bodies pair by pair, keeping the material of each fixture
+fn collide_fixtures((i, body_a): (BodyIndex, &Body), (j, body_b): (BodyIndex, &Body)) -> Vec<ManifoldData> {
+    let mut manifolds = Vec::new();
+
+    for fixture_a in body_a.fixture_bodies() {
+        for fixture_b in body_b.fixture_bodies() {
+            for mut data in collide_bodies((i, &fixture_a), (j, &fixture_b)) {
+                if data.materials.is_none() {
+                    data.materials = Some(if data.pair.0 == i {
+                        (fixture_a.material(), fixture_b.material())
+                    } else {
+                        (fixture_b.material(), fixture_a.material())
+                    });
+                }
+                manifolds.push(data);
+            }
+        }
+    }
+
+    manifolds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::joint::MouseJoint;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    #[cfg(feature = "serialize")]
+    use super::super::joint::RevoluteJoint;
+    #[cfg(feature = "serialize")]
+    use serde_json;
+
+    #[test]
+    fn kinematic_platform_carries_heavy_box() {
+        let mut scene = Scene::new();
+        let mut platform = Body::new(Shape::rect(Vec2::new(200.0, 10.0)), Vec2::new(0.0, 0.0));
+        platform.set_kinematic();
+        platform.velocity = Vec2::new(50.0, 0.0);
+        let platform = scene.add(platform);
+        // 100x100 box at density 1, heavy enough for its inverse mass to compare equal to 0
+        let crate_ = scene.add(Body::new(Shape::rect(Vec2::new(50.0, 50.0)), Vec2::new(0.0, -60.0)));
+        assert!(float_cmp(scene.bodies[crate_].inv_mass, 0.0));
+
+        for _ in 0..120 {
+            scene.step(FRAME_TIME);
+        }
+
+        assert_eq!(scene.bodies[platform].velocity, Vec2::new(50.0, 0.0));
+        assert!((scene.bodies[platform].position.x - 100.0).abs() < 0.01);
+        let body = &scene.bodies[crate_];
+        assert!((body.position.y + 60.0).abs() < 1.0, "{:?}", body.position);
+        assert!(body.position.x > 50.0, "{:?}", body.position);
+    }
+
+    #[test]
+    fn sliding_over_chain_vertices() {
+        for shape in &[Shape::rect(Vec2::new(10.0, 10.0)), Shape::Circle { radius: 10.0 }] {
+            let mut scene = Scene::new();
+            let vertices = vec![Vec2::new(-200.0, 0.0), Vec2::new(-100.0, 0.0), Vec2::new(0.0, 0.0), Vec2::new(200.0, 0.0)];
+            let mut chain = Body::new(Shape::Chain { vertices: vertices, looped: false }, Vec2::new(0.0, 0.0));
+            chain.static_friction = 0.0;
+            chain.dynamic_friction = 0.0;
+            let chain = scene.add(chain);
+            let index = scene.add(Body::new(shape.clone(), Vec2::new(-150.0, -10.0)));
+            scene.bodies[index].velocity = Vec2::new(300.0, 0.0);
+
+            let mut contacts = 0;
+            for _ in 0..60 {
+                for data in scene.collide(chain, index) {
+                    contacts += 1;
+                    assert_eq!(data.pair, (chain, index));
+                    // Flat chain, nothing may push the body back or catch its edge
+                    assert!(data.normal.x.abs() < 1e-3 && data.normal.y < 0.0, "{:?}", data.normal);
+                }
+                scene.step(FRAME_TIME);
+            }
+            assert!(contacts > 30, "{} contacts", contacts);
+
+            let body = &scene.bodies[index];
+            assert!(body.position.x > 100.0, "{:?}", body.position);
+            assert!((body.velocity.x - 300.0).abs() < 1.0, "{:?}", body.velocity);
+            assert!(body.orient.0.abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn locked_axis_stays_fixed() {
+        for &mode in &[PositionCorrection::Linear, PositionCorrection::NonLinearGaussSeidel] {
+            let mut scene = Scene::new();
+            scene.solver.position_correction = mode;
+
+            let mut slider = Body::new(Shape::rect(Vec2::new(20.0, 10.0)), Vec2::new(0.0, 0.0));
+            slider.set_locked_axes(false, true);
+            slider.set_fixed_rotation(true);
+            let slider = scene.add(slider);
+            // Overlapping from the start so position correction has to push on it
+            scene.add(Body::new(Shape::rect(Vec2::new(10.0, 10.0)), Vec2::new(25.0, -15.0)));
+            let ball = scene.add(Body::new(Shape::Circle { radius: 8.0 }, Vec2::new(-60.0, -40.0)));
+            scene.bodies[ball].velocity = Vec2::new(300.0, 200.0);
+
+            let mut mouse = MouseJoint::new((slider, &scene.bodies[slider]), Vec2::new(10.0, 0.0), 1e6);
+            mouse.target = Vec2::new(100.0, -100.0);
+            scene.add_joint(Joint::Mouse(mouse));
+
+            let start = scene.bodies[slider].position;
+            for _ in 0..60 {
+                scene.step(FRAME_TIME);
+                let body = &scene.bodies[slider];
+                assert_eq!(body.position.y, start.y);
+                assert_eq!(body.velocity.y, 0.0);
+                assert_eq!(body.orient.0, 0.0);
+            }
+            assert!(scene.bodies[slider].position.x > 50.0);
+        }
+    }
+
+    // Counts the solver steps by the number of times it is asked for forces
+    struct StepCounter(Rc<Cell<u32>>);
+
+    impl ForceGenerator for StepCounter {
+        fn apply(&mut self, _: &mut [Body], _: Real) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    fn counted_scene() -> (Scene, Rc<Cell<u32>>) {
+        let mut scene = Scene::new();
+        // Exact in binary, so whole multiples of it leave nothing in the accumulator
+        scene.time_step = 1.0 / 64.0;
+        scene.add(Body::new(Shape::Circle { radius: 5.0 }, Vec2::new(0.0, 0.0)));
+        let steps = Rc::new(Cell::new(0));
+        scene.add_force_generator(StepCounter(steps.clone()));
+        (scene, steps)
+    }
+
+    #[test]
+    fn update_takes_whole_steps() {
+        let (mut scene, steps) = counted_scene();
+        let alpha = scene.update(4.0 / 64.0);
+        assert_eq!(steps.get(), 4);
+        assert_eq!(alpha, 0.0);
+
+        // The rest of a step is kept for the next update
+        let alpha = scene.update(1.5 / 64.0);
+        assert_eq!(steps.get(), 5);
+        assert_eq!(alpha, 0.5);
+        scene.update(0.5 / 64.0);
+        assert_eq!(steps.get(), 6);
+
+        // Each step is split into substeps
+        scene.substeps = 3;
+        scene.update(2.0 / 64.0);
+        assert_eq!(steps.get(), 12);
+    }
+
+    #[test]
+    fn update_matches_fixed_steps() {
+        let (mut updated, _) = counted_scene();
+        let (mut stepped, _) = counted_scene();
+        updated.update(5.0 / 64.0);
+        for _ in 0..5 {
+            stepped.step(1.0 / 64.0);
+        }
+        assert_eq!(updated.bodies[0].position, stepped.bodies[0].position);
+        assert_eq!(updated.bodies[0].velocity, stepped.bodies[0].velocity);
+    }
+
+    #[test]
+    fn update_drops_steps_past_max_steps() {
+        let (mut scene, steps) = counted_scene();
+        scene.max_steps = 5;
+        let alpha = scene.update(12.25 / 64.0);
+        assert_eq!(steps.get(), 5);
+        // Only the fraction of a step is kept, the other 7 steps are dropped
+        assert_eq!(alpha, 0.25);
+
+        scene.update(0.0);
+        assert_eq!(steps.get(), 5);
+    }
+
+    #[test]
+    fn interpolated_transform_between_steps() {
+        let mut scene = Scene::new();
+        let index = scene.add(Body::new(Shape::Circle { radius: 5.0 }, Vec2::new(0.0, 0.0)));
+        scene.bodies[index].velocity = Vec2::new(120.0, 0.0);
+        scene.bodies[index].angular_velocity = 3.0;
+
+        // Before any step both ends are the current transform
+        assert_eq!(scene.interpolated_transform(index, 0.5).position, scene.bodies[index].position);
+
+        scene.step(FRAME_TIME);
+        let previous = scene.interpolated_transform(index, 0.0);
+        let current = scene.interpolated_transform(index, 1.0);
+        assert_eq!(previous.position, Vec2::new(0.0, 0.0));
+        assert_eq!(previous.orient, Rad(0.0));
+        assert_eq!(current.position, scene.bodies[index].position);
+        assert_eq!(current.orient, scene.bodies[index].orient);
+
+        let half = scene.interpolated_transform(index, 0.5);
+        assert!(((previous.position + current.position) * 0.5 - half.position).magnitude() < EPSILON);
+        assert!(float_cmp(half.orient.0, current.orient.0 * 0.5));
+    }
+
+    #[cfg(feature = "serialize")]
+    #[test]
+    fn json_round_trip() {
+        let mut scene = Scene::new();
+        let mut ground = Body::new(Shape::rect(Vec2::new(300.0, 20.0)), Vec2::new(0.0, 100.0));
+        ground.set_static();
+        let ground = scene.add(ground);
+        let pendulum = scene.add(Body::new(Shape::rounded_rect(Vec2::new(20.0, 5.0), 2.0), Vec2::new(40.0, -50.0)));
+        scene.add(Body::new(Shape::Capsule { half_height: 15.0, radius: 5.0 }, Vec2::new(-60.0, 40.0)));
+        scene.add(Body::new(Shape::concave(&[
+            Vec2::new(0.0, 0.0), Vec2::new(40.0, 0.0), Vec2::new(40.0, 40.0),
+            Vec2::new(30.0, 40.0), Vec2::new(30.0, 10.0), Vec2::new(0.0, 10.0),
+        ]), Vec2::new(60.0, 20.0)));
+        let joint = RevoluteJoint::new((ground, &scene.bodies[ground]), (pendulum, &scene.bodies[pendulum]), Vec2::new(0.0, -50.0));
+        let handle = scene.add_joint(Joint::Revolute(joint));
+
+        for _ in 0..30 {
+            scene.update(FRAME_TIME);
+        }
+
+        let json = serde_json::to_string(&scene).unwrap();
+        let mut copy: Scene = serde_json::from_str(&json).unwrap();
+
+        for _ in 0..60 {
+            scene.update(FRAME_TIME);
+            copy.update(FRAME_TIME);
+        }
+
+        assert_eq!(scene.bodies.len(), copy.bodies.len());
+        for (a, b) in scene.bodies.iter().zip(&copy.bodies) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.orient, b.orient);
+            assert_eq!(a.velocity, b.velocity);
+            assert_eq!(a.angular_velocity, b.angular_velocity);
+        }
+        assert_eq!(scene.joint_reaction(handle), copy.joint_reaction(handle));
+    }
+}
@@ -1,7 +1,9 @@
-use cgmath::{Vector2, Matrix2};
+use cgmath::{Vector2, Vector3, Matrix2, Matrix3};
 
 pub type Real = f32;
 pub type Vec2 = Vector2<Real>;
+pub type Vec3 = Vector3<Real>;
 pub type Mat2 = Matrix2<Real>;
+pub type Mat3 = Matrix3<Real>;
 
 pub static PI : f32 = ::std::f32::consts::PI;
\ No newline at end of file